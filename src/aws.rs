@@ -1 +1,4 @@
+pub mod parameter_diff;
+pub mod parameter_policy;
 pub mod parameter_store;
+pub mod plaintext_import;
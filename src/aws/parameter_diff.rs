@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::aws::parameter_store::{Parameter, ParameterCollection};
+
+/// What happened to a single key between two sets of parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(Parameter),
+    Removed(Parameter),
+    Modified { old: Parameter, new: Parameter },
+}
+
+/// The differences between two parameter collections.  Keys are
+/// compared relative to each collection's prefix, so that e.g.
+/// /preprod/db/host lines up with /prod/db/host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterDiff {
+    changes: BTreeMap<String, Change>,
+}
+
+impl ParameterDiff {
+    pub fn between(old: &ParameterCollection, new: &ParameterCollection) -> ParameterDiff {
+        ParameterDiff::compare(relative_parameters(old), relative_parameters(new))
+    }
+
+    /// Like `between`, but compare full key names, ignoring the
+    /// collections' prefixes.  This is what you want after an edit
+    /// session, where the keys themselves may have been changed.
+    pub fn between_absolute(old: &ParameterCollection, new: &ParameterCollection) -> ParameterDiff {
+        ParameterDiff::compare(
+            old.parameters()
+                .iter()
+                .map(|(k, v)| (k.clone(), v))
+                .collect(),
+            new.parameters()
+                .iter()
+                .map(|(k, v)| (k.clone(), v))
+                .collect(),
+        )
+    }
+
+    fn compare(
+        old_params: BTreeMap<String, &Parameter>,
+        new_params: BTreeMap<String, &Parameter>,
+    ) -> ParameterDiff {
+        let mut changes = BTreeMap::new();
+
+        for (key, old_param) in &old_params {
+            match new_params.get(key) {
+                Some(new_param) if new_param != old_param => {
+                    changes.insert(
+                        key.clone(),
+                        Change::Modified {
+                            old: (*old_param).clone(),
+                            new: (*new_param).clone(),
+                        },
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    changes.insert(key.clone(), Change::Removed((*old_param).clone()));
+                }
+            }
+        }
+
+        for (key, new_param) in &new_params {
+            if !old_params.contains_key(key) {
+                changes.insert(key.clone(), Change::Added((*new_param).clone()));
+            }
+        }
+
+        return ParameterDiff { changes };
+    }
+
+    pub fn changes(&self) -> &BTreeMap<String, Change> {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The same diff, minus any keys which were removed.
+    pub fn without_removals(&self) -> ParameterDiff {
+        ParameterDiff {
+            changes: self
+                .changes
+                .iter()
+                .filter(|(_key, change)| match change {
+                    Change::Removed(_) => false,
+                    _ => true,
+                })
+                .map(|(key, change)| (key.clone(), change.clone()))
+                .collect(),
+        }
+    }
+
+    /// The keys affected by this diff, grouped into creations,
+    /// updates and deletions.
+    pub fn summary(&self) -> Vec<String> {
+        let mut created = Vec::new();
+        let mut updated = Vec::new();
+        let mut deleted = Vec::new();
+
+        for (key, change) in &self.changes {
+            match change {
+                Change::Added(_) => created.push(key),
+                Change::Modified { .. } => updated.push(key),
+                Change::Removed(_) => deleted.push(key),
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (heading, marker, keys) in &[
+            ("create", "+", created),
+            ("update", "~", updated),
+            ("delete", "-", deleted),
+        ] {
+            if keys.is_empty() {
+                continue;
+            }
+            lines.push(format!("Keys to {} ({}):", heading, keys.len()));
+            for key in keys {
+                lines.push(format!("  {} {}", marker, key));
+            }
+        }
+
+        lines
+    }
+
+    /// A human-readable description of every change, one line per
+    /// difference.  SecureString values are masked unless `reveal` is
+    /// set.
+    pub fn render(&self, reveal: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for (key, change) in &self.changes {
+            match change {
+                Change::Added(param) => lines.push(format!(
+                    "+ {} ({}): {}",
+                    key,
+                    param.parameter_type(),
+                    param.display_value(reveal)
+                )),
+                Change::Removed(param) => lines.push(format!(
+                    "- {} ({}): {}",
+                    key,
+                    param.parameter_type(),
+                    param.display_value(reveal)
+                )),
+                Change::Modified { old, new } => {
+                    for line in describe_modification(old, new, reveal) {
+                        lines.push(format!("~ {}: {}", key, line));
+                    }
+                }
+            }
+        }
+
+        lines
+    }
+}
+
+/// Describe how a parameter changed between `old` and `new`, one line
+/// per changed aspect.  Values are masked unless `reveal` is set.
+pub fn describe_modification(old: &Parameter, new: &Parameter, reveal: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    if old.parameter_type() != new.parameter_type() {
+        lines.push(format!(
+            "type changed: {} => {}",
+            old.parameter_type(),
+            new.parameter_type()
+        ));
+    }
+    if old.value() != new.value() {
+        lines.push(format!(
+            "value changed: {} => {}",
+            old.display_value(reveal),
+            new.display_value(reveal)
+        ));
+    }
+    for (name, old_attribute, new_attribute) in old.attributes().differences(new.attributes()) {
+        lines.push(format!(
+            "{} changed: {} => {}",
+            name, old_attribute, new_attribute
+        ));
+    }
+
+    lines
+}
+
+/// Merge our edits (`mine`) of `base` with somebody else's edits
+/// (`theirs`).  Keys which only one side changed take that side's
+/// version.  Keys which both sides changed differently are conflicts:
+/// the merged result keeps our version, and the key is returned in the
+/// list of conflicts so that the user can resolve it.
+pub fn three_way_merge(
+    base: &ParameterCollection,
+    theirs: &ParameterCollection,
+    mine: &ParameterCollection,
+) -> (ParameterCollection, Vec<String>) {
+    let mut merged = ParameterCollection::new(mine.prefix().clone());
+    let mut conflicts = Vec::new();
+
+    let keys: BTreeSet<&String> = base
+        .parameters()
+        .keys()
+        .chain(theirs.parameters().keys())
+        .chain(mine.parameters().keys())
+        .collect();
+
+    for key in keys {
+        let base_param = base.parameters().get(key);
+        let their_param = theirs.parameters().get(key);
+        let my_param = mine.parameters().get(key);
+
+        let winner = if my_param == base_param {
+            their_param
+        } else if their_param == base_param || their_param == my_param {
+            my_param
+        } else {
+            conflicts.push(key.clone());
+            my_param
+        };
+
+        if let Some(param) = winner {
+            merged.insert(key.clone(), param.without_store_metadata());
+        }
+    }
+
+    (merged, conflicts)
+}
+
+// Index a collection's parameters by their key relative to the
+// collection's prefix.
+fn relative_parameters(collection: &ParameterCollection) -> BTreeMap<String, &Parameter> {
+    collection
+        .parameters()
+        .iter()
+        .map(|(key, param)| {
+            let relative = key
+                .strip_prefix(collection.prefix().as_str())
+                .unwrap_or(key);
+            (relative.to_string(), param)
+        })
+        .collect()
+}
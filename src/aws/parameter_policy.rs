@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::error;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::aws::parameter_store::{ParameterError, ParameterTier};
+
+/// A Parameter Store policy, as written in the YAML.  Policies are
+/// only available on Advanced (or Intelligent-Tiering) tier parameters.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum ParameterPolicy {
+    /// Delete the parameter at the given time, e.g.
+    /// 2021-12-01T00:00:00Z.
+    Expiration { timestamp: String },
+    /// Notify (through EventBridge) some time before the parameter
+    /// expires.
+    ExpirationNotification { before: u32, unit: PolicyTimeUnit },
+    /// Notify if the parameter hasn't been changed for a while.
+    NoChangeNotification { after: u32, unit: PolicyTimeUnit },
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum PolicyTimeUnit {
+    Days,
+    Hours,
+}
+
+// The JSON representation Parameter Store uses for a single policy.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AwsPolicy {
+    #[serde(rename = "Type")]
+    policy_type: String,
+    version: String,
+    attributes: BTreeMap<String, serde_json::Value>,
+}
+
+impl PolicyTimeUnit {
+    fn duration(&self, amount: u32) -> Duration {
+        match self {
+            PolicyTimeUnit::Days => Duration::days(amount.into()),
+            PolicyTimeUnit::Hours => Duration::hours(amount.into()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            PolicyTimeUnit::Days => "Days",
+            PolicyTimeUnit::Hours => "Hours",
+        }
+    }
+}
+
+impl ParameterPolicy {
+    /// When the parameter expires, if this is an Expiration policy
+    /// with a valid timestamp.
+    pub fn expiration(&self) -> Option<DateTime<Utc>> {
+        match self {
+            ParameterPolicy::Expiration { timestamp } => parse_timestamp(timestamp).ok(),
+            _ => None,
+        }
+    }
+
+    /// Parse a policy as returned by the Parameter Store API.
+    pub fn from_aws_json(policy_text: &str) -> Result<ParameterPolicy, Box<dyn error::Error>> {
+        let raw: AwsPolicy = serde_json::from_str(policy_text)?;
+        let attribute = |name: &str| -> Result<String, ParameterError> {
+            match raw.attributes.get(name) {
+                Some(serde_json::Value::String(value)) => Ok(value.clone()),
+                Some(other) => Ok(other.to_string()),
+                None => Err(ParameterError::InvalidPolicy(format!(
+                    "{} policy without {}",
+                    raw.policy_type, name
+                ))),
+            }
+        };
+        let unit = || -> Result<PolicyTimeUnit, ParameterError> {
+            match attribute("Unit")?.as_str() {
+                "Days" => Ok(PolicyTimeUnit::Days),
+                "Hours" => Ok(PolicyTimeUnit::Hours),
+                other => Err(ParameterError::InvalidPolicy(format!(
+                    "unknown time unit {:?}",
+                    other
+                ))),
+            }
+        };
+
+        match raw.policy_type.as_str() {
+            "Expiration" => Ok(ParameterPolicy::Expiration {
+                timestamp: attribute("Timestamp")?,
+            }),
+            "ExpirationNotification" => Ok(ParameterPolicy::ExpirationNotification {
+                before: attribute("Before")?.parse()?,
+                unit: unit()?,
+            }),
+            "NoChangeNotification" => Ok(ParameterPolicy::NoChangeNotification {
+                after: attribute("After")?.parse()?,
+                unit: unit()?,
+            }),
+            other => Err(
+                ParameterError::InvalidPolicy(format!("unknown policy type {:?}", other)).into(),
+            ),
+        }
+    }
+
+    fn to_aws(&self) -> AwsPolicy {
+        let (policy_type, attributes) = match self {
+            ParameterPolicy::Expiration { timestamp } => {
+                ("Expiration", vec![("Timestamp", timestamp.clone())])
+            }
+            ParameterPolicy::ExpirationNotification { before, unit } => (
+                "ExpirationNotification",
+                vec![
+                    ("Before", before.to_string()),
+                    ("Unit", unit.name().to_string()),
+                ],
+            ),
+            ParameterPolicy::NoChangeNotification { after, unit } => (
+                "NoChangeNotification",
+                vec![
+                    ("After", after.to_string()),
+                    ("Unit", unit.name().to_string()),
+                ],
+            ),
+        };
+
+        AwsPolicy {
+            policy_type: policy_type.to_string(),
+            version: "1.0".to_string(),
+            attributes: attributes
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), serde_json::Value::String(value)))
+                .collect(),
+        }
+    }
+}
+
+/// Render a set of policies as the JSON document PutParameter expects.
+pub fn to_aws_json(policies: &[ParameterPolicy]) -> Result<String, Box<dyn error::Error>> {
+    let raw: Vec<AwsPolicy> = policies.iter().map(|policy| policy.to_aws()).collect();
+    Ok(serde_json::to_string(&raw)?)
+}
+
+/// Check a parameter's policies before sending them off: policies need
+/// the Advanced tier (or Intelligent-Tiering, which picks Advanced for
+/// parameters with policies), expiration must lie in the future, and
+/// notifications need a window that makes sense.
+pub fn validate(
+    policies: &[ParameterPolicy],
+    tier: Option<ParameterTier>,
+) -> Result<(), ParameterError> {
+    if policies.is_empty() {
+        return Ok(());
+    }
+
+    if !matches!(
+        tier,
+        Some(ParameterTier::Advanced) | Some(ParameterTier::IntelligentTiering)
+    ) {
+        return Err(ParameterError::InvalidPolicy(
+            "policies require the Advanced or Intelligent-Tiering tier".to_string(),
+        ));
+    }
+
+    let now = Utc::now();
+    let mut expiration: Option<DateTime<Utc>> = None;
+
+    for policy in policies {
+        if let ParameterPolicy::Expiration { timestamp } = policy {
+            let time = parse_timestamp(timestamp)?;
+            if time <= now {
+                return Err(ParameterError::InvalidPolicy(format!(
+                    "expiration {} is in the past",
+                    timestamp
+                )));
+            }
+            if expiration.is_some() {
+                return Err(ParameterError::InvalidPolicy(
+                    "only one Expiration policy is allowed".to_string(),
+                ));
+            }
+            expiration = Some(time);
+        }
+    }
+
+    for policy in policies {
+        match policy {
+            ParameterPolicy::ExpirationNotification { before, unit } => {
+                let expiration = expiration.ok_or_else(|| {
+                    ParameterError::InvalidPolicy(
+                        "ExpirationNotification needs an Expiration policy".to_string(),
+                    )
+                })?;
+                if *before == 0 {
+                    return Err(ParameterError::InvalidPolicy(
+                        "ExpirationNotification needs a window of at least 1".to_string(),
+                    ));
+                }
+                if expiration - unit.duration(*before) <= now {
+                    return Err(ParameterError::InvalidPolicy(format!(
+                        "ExpirationNotification {} {} before expiration would already have passed",
+                        before,
+                        unit.name()
+                    )));
+                }
+            }
+            ParameterPolicy::NoChangeNotification { after, .. } if *after == 0 => {
+                return Err(ParameterError::InvalidPolicy(
+                    "NoChangeNotification needs a window of at least 1".to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, ParameterError> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| {
+            ParameterError::InvalidPolicy(format!(
+                "invalid timestamp {:?}, expected e.g. 2021-12-01T00:00:00Z",
+                timestamp
+            ))
+        })
+}
@@ -1,21 +1,99 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error;
 use std::fmt;
 use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
 
+use chrono::{DateTime, Utc};
 use regex::Regex;
-use rusoto_ssm::{GetParametersByPathRequest, PutParameterRequest, Ssm, SsmClient};
 use serde::{Deserialize, Serialize};
-use tokio::runtime;
 
+use crate::aws::parameter_diff::{Change, ParameterDiff};
+use crate::aws::parameter_policy::{self, ParameterPolicy};
+use crate::backend::parameter_backend::ParameterBackend;
 use crate::flags::operation_mode::OperationMode;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "FlatParameter", into = "FlatParameter")]
 pub struct Parameter {
-    #[serde(rename = "value")]
     parameter_value: String,
+    parameter_type: ParameterType,
+    attributes: ParameterAttributes,
+    // Bookkeeping from the store, used to detect concurrent
+    // modifications.  It's never part of the YAML, and isn't taken
+    // into account when comparing parameters.
+    version: Option<i64>,
+    last_modified_date: Option<f64>,
+    last_modified_user: Option<String>,
+}
+
+// The shape of a parameter in YAML: value, type and attributes all at
+// the same level.  (serde's `flatten` would do the same, but it makes
+// serde_yaml reject unquoted numbers as values.)
+#[derive(Serialize, Deserialize)]
+struct FlatParameter {
+    value: String,
     #[serde(rename = "type")]
     parameter_type: ParameterType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tier: Option<ParameterTier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowed_pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_type: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    tags: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    policies: Vec<ParameterPolicy>,
+}
+
+impl From<FlatParameter> for Parameter {
+    fn from(flat: FlatParameter) -> Parameter {
+        Parameter {
+            parameter_value: flat.value,
+            parameter_type: flat.parameter_type,
+            attributes: ParameterAttributes {
+                description: flat.description,
+                tier: flat.tier,
+                key_id: flat.key_id,
+                allowed_pattern: flat.allowed_pattern,
+                data_type: flat.data_type,
+                tags: flat.tags,
+                policies: flat.policies,
+            },
+            version: None,
+            last_modified_date: None,
+            last_modified_user: None,
+        }
+    }
+}
+
+impl From<Parameter> for FlatParameter {
+    fn from(param: Parameter) -> FlatParameter {
+        FlatParameter {
+            value: param.parameter_value,
+            parameter_type: param.parameter_type,
+            description: param.attributes.description,
+            tier: param.attributes.tier,
+            key_id: param.attributes.key_id,
+            allowed_pattern: param.attributes.allowed_pattern,
+            data_type: param.attributes.data_type,
+            tags: param.attributes.tags,
+            policies: param.attributes.policies,
+        }
+    }
+}
+
+impl PartialEq for Parameter {
+    fn eq(&self, other: &Self) -> bool {
+        self.parameter_value == other.parameter_value
+            && self.parameter_type == other.parameter_type
+            && self.attributes == other.attributes
+    }
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
@@ -25,6 +103,33 @@ pub enum ParameterType {
     StringList,
 }
 
+/// The optional settings of a parameter besides its value and type.
+/// Anything left unset means "whatever Parameter Store defaults to",
+/// and is left out of the YAML.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParameterAttributes {
+    pub description: Option<String>,
+    pub tier: Option<ParameterTier>,
+    /// The KMS key used to encrypt a SecureString, if it's not the
+    /// account's default key.
+    pub key_id: Option<String>,
+    pub allowed_pattern: Option<String>,
+    /// Either "text" (the default) or "aws:ec2:image".
+    pub data_type: Option<String>,
+    /// Resource tags, which aren't versioned along with the value.
+    pub tags: BTreeMap<String, String>,
+    /// Expiration and notification policies (Advanced tier only).
+    pub policies: Vec<ParameterPolicy>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum ParameterTier {
+    Standard,
+    Advanced,
+    #[serde(rename = "Intelligent-Tiering")]
+    IntelligentTiering,
+}
+
 #[derive(Debug)]
 pub enum ParameterError {
     InvalidParameterType(
@@ -35,7 +140,35 @@ pub enum ParameterError {
         /// Contains a more detailed error description
         String,
     ),
-    NonexistentKey(String),
+    InvalidParameterTier(
+        /// Contains the malformed input for debugging purposes
+        String,
+    ),
+    InvalidTagFilter(
+        /// Contains the malformed input for debugging purposes
+        String,
+    ),
+    InvalidPolicy(
+        /// Contains a more detailed error description
+        String,
+    ),
+    InvalidLabel(
+        /// Contains a more detailed error description
+        String,
+    ),
+    InvalidInput(
+        /// Contains a more detailed error description
+        String,
+    ),
+    EnvVarCollision(
+        /// Contains a more detailed error description
+        String,
+    ),
+    ParameterAlreadyExists(String),
+    Unsupported(
+        /// Describes the operation which isn't available
+        String,
+    ),
 }
 
 // TODO i'm sure this can be made less ugly.
@@ -53,6 +186,20 @@ impl fmt::Display for ParameterType {
     }
 }
 
+impl fmt::Display for ParameterTier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ParameterTier::Standard => "Standard",
+                ParameterTier::Advanced => "Advanced",
+                ParameterTier::IntelligentTiering => "Intelligent-Tiering",
+            }
+        )
+    }
+}
+
 impl fmt::Display for ParameterError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -60,7 +207,24 @@ impl fmt::Display for ParameterError {
                 write!(f, "invalid AWS Parameter Store parameter type: {:?}", input)
             }
             ParameterError::InvalidPathPrefix(desc) => write!(f, "invalid path prefix: {}", desc),
-            ParameterError::NonexistentKey(desc) => write!(f, "key {} not found in list of parameters.  Use `create` command to add new parameters.", desc),
+            ParameterError::InvalidParameterTier(input) => {
+                write!(f, "invalid AWS Parameter Store parameter tier: {:?}", input)
+            }
+            ParameterError::InvalidPolicy(desc) => write!(f, "invalid parameter policy: {}", desc),
+            ParameterError::InvalidTagFilter(input) => write!(
+                f,
+                "invalid tag filter {:?}, expected key=value or key",
+                input
+            ),
+            ParameterError::InvalidLabel(desc) => write!(f, "invalid label: {}", desc),
+            ParameterError::InvalidInput(desc) => write!(f, "invalid input: {}", desc),
+            ParameterError::EnvVarCollision(desc) => {
+                write!(f, "conflicting environment variable names: {}", desc)
+            }
+            ParameterError::ParameterAlreadyExists(key) => {
+                write!(f, "key {} already exists, refusing to overwrite it.", key)
+            }
+            ParameterError::Unsupported(desc) => write!(f, "not supported: {}", desc),
         }
     }
 }
@@ -80,6 +244,89 @@ impl FromStr for ParameterType {
     }
 }
 
+impl FromStr for ParameterTier {
+    type Err = ParameterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Standard" => Ok(ParameterTier::Standard),
+            "Advanced" => Ok(ParameterTier::Advanced),
+            "Intelligent-Tiering" => Ok(ParameterTier::IntelligentTiering),
+            _ => Err(ParameterError::InvalidParameterTier(s.to_string())),
+        }
+    }
+}
+
+impl ParameterAttributes {
+    /// The attributes which differ between `self` and `other`, as
+    /// (name, old, new) triples ready for display.
+    pub fn differences(&self, other: &ParameterAttributes) -> Vec<(&'static str, String, String)> {
+        let fields = vec![
+            (
+                "description",
+                self.description.clone(),
+                other.description.clone(),
+            ),
+            (
+                "tier",
+                self.tier.map(|tier| tier.to_string()),
+                other.tier.map(|tier| tier.to_string()),
+            ),
+            ("key_id", self.key_id.clone(), other.key_id.clone()),
+            (
+                "allowed_pattern",
+                self.allowed_pattern.clone(),
+                other.allowed_pattern.clone(),
+            ),
+            ("data_type", self.data_type.clone(), other.data_type.clone()),
+            (
+                "tags",
+                describe_tags(&self.tags),
+                describe_tags(&other.tags),
+            ),
+            (
+                "policies",
+                describe_policies(&self.policies),
+                describe_policies(&other.policies),
+            ),
+        ];
+
+        let describe =
+            |value: Option<String>| value.map_or("(none)".to_string(), |v| format!("{:?}", v));
+
+        fields
+            .into_iter()
+            .filter(|(_name, old, new)| old != new)
+            .map(|(name, old, new)| (name, describe(old), describe(new)))
+            .collect()
+    }
+}
+
+// Tags as a single line of key=value pairs, or nothing if there are
+// no tags.
+fn describe_tags(tags: &BTreeMap<String, String>) -> Option<String> {
+    if tags.is_empty() {
+        return None;
+    }
+
+    Some(
+        tags.iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>()
+            .join(", "),
+    )
+}
+
+// Policies in the same JSON form Parameter Store uses, or nothing if
+// there are no policies.
+fn describe_policies(policies: &[ParameterPolicy]) -> Option<String> {
+    if policies.is_empty() {
+        return None;
+    }
+
+    parameter_policy::to_aws_json(policies).ok()
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct ParameterCollection {
     #[serde(default, skip_serializing_if = "String::is_empty")]
@@ -92,8 +339,137 @@ impl Parameter {
         return Parameter {
             parameter_value,
             parameter_type: ParameterType::from_str(&parameter_type).unwrap(),
+            attributes: ParameterAttributes::default(),
+            version: None,
+            last_modified_date: None,
+            last_modified_user: None,
         };
     }
+
+    pub fn with_attributes(mut self, attributes: ParameterAttributes) -> Parameter {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Attach the store's bookkeeping about this parameter's current
+    /// version.
+    pub fn with_store_metadata(
+        mut self,
+        version: Option<i64>,
+        last_modified_date: Option<f64>,
+        last_modified_user: Option<String>,
+    ) -> Parameter {
+        self.version = version;
+        self.last_modified_date = last_modified_date;
+        self.last_modified_user = last_modified_user;
+        self
+    }
+
+    /// A copy of this parameter without the store's bookkeeping, for
+    /// when it is about to be written somewhere new.
+    pub fn without_store_metadata(&self) -> Parameter {
+        self.clone().with_store_metadata(None, None, None)
+    }
+
+    pub fn value(&self) -> &String {
+        &self.parameter_value
+    }
+
+    pub fn parameter_type(&self) -> &ParameterType {
+        &self.parameter_type
+    }
+
+    pub fn attributes(&self) -> &ParameterAttributes {
+        &self.attributes
+    }
+
+    pub fn version(&self) -> &Option<i64> {
+        &self.version
+    }
+
+    pub fn last_modified_date(&self) -> &Option<f64> {
+        &self.last_modified_date
+    }
+
+    pub fn last_modified_user(&self) -> &Option<String> {
+        &self.last_modified_user
+    }
+
+    /// The value as it's safe to show on a terminal: SecureString
+    /// values are masked unless `reveal` is set.
+    pub fn display_value(&self, reveal: bool) -> String {
+        if self.parameter_type == ParameterType::SecureString && !reveal {
+            "********".to_string()
+        } else {
+            format!("{:?}", self.parameter_value)
+        }
+    }
+}
+
+/// A single historical version of a parameter, as returned by a
+/// backend's history lookup.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ParameterVersion {
+    version: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified_date: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified_user: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    labels: Vec<String>,
+    parameter: Parameter,
+}
+
+impl ParameterVersion {
+    pub fn new(
+        version: i64,
+        parameter: Parameter,
+        last_modified_date: Option<f64>,
+        last_modified_user: Option<String>,
+        labels: Vec<String>,
+    ) -> ParameterVersion {
+        return ParameterVersion {
+            version,
+            last_modified_date,
+            last_modified_user,
+            labels,
+            parameter,
+        };
+    }
+
+    pub fn version(&self) -> &i64 {
+        &self.version
+    }
+
+    pub fn last_modified_date(&self) -> &Option<f64> {
+        &self.last_modified_date
+    }
+
+    pub fn last_modified_user(&self) -> &Option<String> {
+        &self.last_modified_user
+    }
+
+    pub fn labels(&self) -> &Vec<String> {
+        &self.labels
+    }
+
+    pub fn labels_mut(&mut self) -> &mut Vec<String> {
+        &mut self.labels
+    }
+
+    pub fn parameter(&self) -> &Parameter {
+        &self.parameter
+    }
+
+    /// The parameter as it was at this version, including the store's
+    /// bookkeeping.
+    pub fn to_parameter(&self) -> Parameter {
+        self.parameter.clone().with_store_metadata(
+            Some(self.version),
+            self.last_modified_date,
+            self.last_modified_user.clone(),
+        )
+    }
 }
 
 impl ParameterCollection {
@@ -104,6 +480,10 @@ impl ParameterCollection {
         };
     }
 
+    pub fn insert(&mut self, key: String, parameter: Parameter) {
+        self.parameters.insert(key, parameter);
+    }
+
     pub fn parameters(&self) -> &HashMap<String, Parameter> {
         &self.parameters
     }
@@ -114,72 +494,93 @@ impl ParameterCollection {
 }
 
 pub fn get_parameters_by_path(
+    backend: &dyn ParameterBackend,
     path_prefix: String,
-    debug: bool,
 ) -> Result<ParameterCollection, Box<dyn error::Error>> {
-    let raw_parameters = raw_parameters_by_path(path_prefix.clone(), debug)?;
     let mut result = ParameterCollection::new(path_prefix.clone());
+    result.parameters = backend.parameters_by_path(&path_prefix)?;
 
-    for raw_param in &raw_parameters {
-        result.parameters.insert(
-            raw_param.name.clone().unwrap(),
-            Parameter::new(
-                raw_param.value.clone().unwrap_or_default(),
-                raw_param.type_.clone().unwrap_or_default(),
-            ),
-        );
+    return Ok(result);
+}
+
+// select_keys returns the keys a per-key command should act on: just
+// `name` itself, or with `recursive` every key under the prefix `name`.
+pub fn select_keys(
+    backend: &dyn ParameterBackend,
+    name: &str,
+    recursive: bool,
+) -> Result<Vec<String>, Box<dyn error::Error>> {
+    if !recursive {
+        return Ok(vec![name.to_string()]);
     }
 
-    return Ok(result);
+    let mut keys: Vec<String> = backend.parameters_by_path(name)?.into_keys().collect();
+    keys.sort();
+    Ok(keys)
 }
 
-fn raw_parameters_by_path(
-    path_prefix: String,
-    debug: bool,
-) -> Result<Vec<rusoto_ssm::Parameter>, Box<dyn error::Error>> {
-    let mut rt = runtime::Builder::new()
-        .threaded_scheduler()
-        .enable_all()
-        .build()?;
-
-    let client = SsmClient::new(Default::default());
-
-    // Super awful, let's chuck out all the lovingly-crafted futures
-    // work and just run the request in a blocking fashion on the main
-    // thread.
-    let mut req = GetParametersByPathRequest {
-        path: path_prefix.to_owned(),
-        with_decryption: Some(true),
-        recursive: Some(true),
-        ..GetParametersByPathRequest::default()
-    };
-
-    let mut res = rt.block_on(client.get_parameters_by_path(req.clone()))?;
-
-    let mut parameters: Vec<rusoto_ssm::Parameter> = Vec::new();
-    if let Some(new_params) = res.parameters {
-        parameters.extend(new_params.into_iter());
-    }
-
-    // Get next set of parameters if there's a next_token.
-    while let Some(next_token) = res.next_token {
-        req.next_token = Some(next_token);
-        res = rt.block_on(client.get_parameters_by_path(req.clone()))?;
-
-        if let Some(new_params) = res.parameters {
-            parameters.extend(new_params.into_iter());
-        }
+// check_label enforces Parameter Store's rules for version labels, so
+// that mistakes are caught before anything is written.
+pub fn check_label(label: &str) -> Result<(), ParameterError> {
+    let re = Regex::new(r"^[a-zA-Z0-9_.-]{1,100}$").unwrap();
+    if !re.is_match(label) {
+        return Err(ParameterError::InvalidLabel(format!(
+            "{:?} must be 1 to 100 letters, numbers, periods, hyphens or underscores",
+            label
+        )));
     }
 
-    if debug {
-        eprintln!("raw_parameters_by_path: received from API:");
-        eprintln!("{:?}", parameters);
+    let lowercase = label.to_lowercase();
+    if label.starts_with(|c: char| c.is_ascii_digit())
+        || lowercase.starts_with("aws")
+        || lowercase.starts_with("ssm")
+    {
+        return Err(ParameterError::InvalidLabel(format!(
+            "{:?} must not begin with a number, \"aws\" or \"ssm\"",
+            label
+        )));
     }
 
-    return Ok(parameters);
+    Ok(())
+}
+
+/// Find the version of a parameter which carries `label`.
+pub fn labelled_version<'a>(
+    history: &'a [ParameterVersion],
+    label: &str,
+) -> Option<&'a ParameterVersion> {
+    history
+        .iter()
+        .find(|version| version.labels().iter().any(|l| l == label))
 }
 
-fn check_path(parameter_path: String) -> Result<(), Box<dyn error::Error>> {
+// get_parameters_by_label is like get_parameters_by_path, but takes
+// each parameter as it was at the version carrying `label`.  Tags
+// aren't versioned, so the current ones are kept.  Parameters which
+// have no version with that label are left out.
+pub fn get_parameters_by_label(
+    backend: &dyn ParameterBackend,
+    path_prefix: String,
+    label: &str,
+) -> Result<ParameterCollection, Box<dyn error::Error>> {
+    let mut result = ParameterCollection::new(path_prefix.clone());
+
+    for (key, current) in backend.parameters_by_path(&path_prefix)? {
+        let history = backend.parameter_history(&key)?;
+        match labelled_version(&history, label) {
+            Some(version) => {
+                let mut attributes = version.parameter().attributes().clone();
+                attributes.tags = current.attributes().tags.clone();
+                result.insert(key, version.to_parameter().with_attributes(attributes));
+            }
+            None => eprintln!("{} has no version labelled {}, skipping.", key, label),
+        }
+    }
+
+    return Ok(result);
+}
+
+pub fn check_path(parameter_path: String) -> Result<(), Box<dyn error::Error>> {
     let re = Regex::new(r"^/[a-zA-Z0-9_.-]").unwrap();
     if !(re.is_match(&parameter_path)) {
         return Err(ParameterError::InvalidPathPrefix("must begin with slash".to_string()).into());
@@ -213,22 +614,17 @@ pub fn reroot_parameters(
     for (key, param) in source.parameters() {
         // It's okay to panic here, because things are weird if the
         // search prefix doesn't match all the keys in a blob.
+        let remainder = key.strip_prefix(source.prefix()).unwrap();
         let mut new_key_parts = Vec::new();
         new_key_parts.push(destination.clone());
-        new_key_parts.push(
-            key.clone()
-                .strip_prefix(source.prefix())
-                .unwrap()
-                .to_string(),
-        );
+        // Names without a leading slash, e.g. from a plaintext import,
+        // leave nothing to separate them from the destination.
+        if !remainder.is_empty() && !remainder.starts_with('/') {
+            new_key_parts.push("/".to_string());
+        }
+        new_key_parts.push(remainder.to_string());
         new_key = new_key_parts.join("");
-        new_params.insert(
-            new_key,
-            Parameter {
-                parameter_value: param.parameter_value.clone(),
-                parameter_type: param.parameter_type.clone(),
-            },
-        );
+        new_params.insert(new_key, param.without_store_metadata());
     }
 
     return Ok(ParameterCollection {
@@ -238,33 +634,14 @@ pub fn reroot_parameters(
 }
 
 pub fn push_new_parameters(
+    backend: &dyn ParameterBackend,
     parameters: ParameterCollection,
     operation_mode: OperationMode,
 ) -> Result<(), Box<dyn error::Error>> {
-    let mut rt = runtime::Builder::new()
-        .threaded_scheduler()
-        .enable_all()
-        .build()?;
-
-    let client = SsmClient::new(Default::default());
-
-    let mut req: PutParameterRequest;
-
     for (key, param) in parameters.parameters() {
         if operation_mode == OperationMode::ReadWrite {
             eprintln!("Creating key {}...", key);
-            req = PutParameterRequest {
-                data_type: Some("text".to_string()),
-                name: key.to_string(),
-                type_: Some(param.parameter_type.to_string()),
-                value: param.parameter_value.clone(),
-                overwrite: Some(false),
-                ..PutParameterRequest::default()
-            };
-
-            // TODO catch specific ParameterAlreadyExists error,
-            // because that's a user's fault.
-            rt.block_on(client.put_parameter(req.clone()))?;
+            backend.put_parameter(key, param, false)?;
         } else {
             eprintln!("[DRY-RUN] Would create key {}...", key);
         }
@@ -272,64 +649,172 @@ pub fn push_new_parameters(
     Ok(())
 }
 
-// push_updated_parameters should be called after interactively
-// modifying a set of parameters.  We also want the original
-// parameters so that we can do a comparison.  We don't want the user
-// sneaking in new parameters or making a typo and saving /foo to
-// /fooprime accidentally.
-pub fn push_updated_parameters(
-    old_parameters: ParameterCollection,
-    new_parameters: ParameterCollection,
+// delete_parameters removes the given keys from the store.  Keys
+// which have disappeared by the time we get to them are reported, but
+// aren't considered an error.
+pub fn delete_parameters(
+    backend: &dyn ParameterBackend,
+    keys: Vec<String>,
     operation_mode: OperationMode,
 ) -> Result<(), Box<dyn error::Error>> {
-    let mut updated_parameters: HashMap<String, Parameter> = HashMap::new();
-
-    for (key, new_param) in new_parameters.parameters() {
-        match old_parameters.parameters().get(key) {
-            Some(old_param) => {
-                // alright, the key exists in the old hashmap
-                if old_param.parameter_type != new_param.parameter_type
-                    || old_param.parameter_value != new_param.parameter_value
-                {
-                    // okay, something has changed.
-                    updated_parameters.insert(
-                        key.clone(),
-                        Parameter {
-                            parameter_type: new_param.parameter_type.clone(),
-                            parameter_value: new_param.parameter_value.clone(),
-                        },
-                    );
-                }
+    if operation_mode == OperationMode::ReadOnly {
+        for key in &keys {
+            eprintln!("[DRY-RUN] Would delete key {}...", key);
+        }
+        return Ok(());
+    }
+
+    eprintln!("Deleting {} keys...", keys.len());
+    let deleted = backend.delete_parameters(&keys)?;
+
+    for key in &keys {
+        if deleted.contains(key) {
+            eprintln!("Deleted key {}.", key);
+        } else {
+            eprintln!("Key {} was not found, skipping.", key);
+        }
+    }
+    Ok(())
+}
+
+// move_parameters writes the parameters to their new location under
+// `destination`, and deletes the originals only once every single new
+// key was written successfully.  If a write fails midway, the keys we
+// managed to create are deleted again, so that the store is left the
+// way we found it.
+pub fn move_parameters(
+    backend: &dyn ParameterBackend,
+    source: ParameterCollection,
+    destination: String,
+    operation_mode: OperationMode,
+) -> Result<(), Box<dyn error::Error>> {
+    if source.prefix() == &destination {
+        return Err(ParameterError::InvalidPathPrefix(
+            "destination must differ from source".to_string(),
+        )
+        .into());
+    }
+
+    let moved = reroot_parameters(source.clone(), destination)?;
+    let original_keys: Vec<String> = source.parameters().keys().cloned().collect();
+
+    if operation_mode == OperationMode::ReadOnly {
+        push_new_parameters(backend, moved, operation_mode.clone())?;
+        return delete_parameters(backend, original_keys, operation_mode);
+    }
+
+    let mut created: Vec<String> = Vec::new();
+    for (key, param) in moved.parameters() {
+        eprintln!("Creating key {}...", key);
+        if let Err(failure) = backend.put_parameter(key, param, false) {
+            eprintln!("Could not create key {}: {}", key, failure);
+            if !created.is_empty() {
+                eprintln!(
+                    "Cleaning up the {} keys created so far, the originals are untouched...",
+                    created.len()
+                );
+                backend.delete_parameters(&created)?;
             }
-            None => return Err(ParameterError::NonexistentKey(key.to_string()).into()),
+            return Err(failure);
         }
+        created.push(key.clone());
     }
 
-    let mut rt = runtime::Builder::new()
-        .threaded_scheduler()
-        .enable_all()
-        .build()?;
+    delete_parameters(backend, original_keys, operation_mode)
+}
 
-    let client = SsmClient::new(Default::default());
+/// Format a store timestamp (seconds since the epoch) for humans.
+pub fn format_timestamp(seconds: f64) -> String {
+    timestamp_to_datetime(seconds)
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string()
+}
 
-    let mut req: PutParameterRequest;
+/// Convert a store timestamp (seconds since the epoch) to a date.
+pub fn timestamp_to_datetime(seconds: f64) -> DateTime<Utc> {
+    DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_secs_f64(seconds))
+}
 
-    for (key, param) in updated_parameters {
-        if operation_mode == OperationMode::ReadWrite {
-            eprintln!("Updating key {}...", key);
-            req = PutParameterRequest {
-                data_type: Some("text".to_string()),
-                name: key,
-                type_: Some(param.parameter_type.to_string()),
-                value: param.parameter_value,
-                overwrite: Some(true),
-                ..PutParameterRequest::default()
-            };
-
-            rt.block_on(client.put_parameter(req.clone()))?;
-        } else {
-            eprintln!("[DRY-RUN] Would update key {}...", key);
+// find_conflicts returns the keys touched by `diff` which were changed
+// in the store by someone else since `base` was fetched.  This is
+// detected by comparing version numbers, so it's cheap but relies on
+// `base` having come from the same backend.
+pub fn find_conflicts(
+    backend: &dyn ParameterBackend,
+    base: &ParameterCollection,
+    diff: &ParameterDiff,
+) -> Result<Vec<String>, Box<dyn error::Error>> {
+    let mut conflicts: Vec<String> = Vec::new();
+
+    for key in diff.changes().keys() {
+        let base_version = base.parameters().get(key).and_then(|p| p.version);
+        let current_version = backend.get_parameter(key)?.and_then(|p| p.version);
+
+        if base_version != current_version {
+            conflicts.push(key.clone());
         }
     }
+
+    Ok(conflicts)
+}
+
+// validate_changes checks the parameters which a diff would create or
+// modify, so that mistakes are caught before anything is written.
+// Policies are only checked when they change, because an untouched
+// parameter may legitimately carry a policy that is about to expire.
+pub fn validate_changes(diff: &ParameterDiff) -> Result<(), Box<dyn error::Error>> {
+    for change in diff.changes().values() {
+        let (old, new) = match change {
+            Change::Added(new) => (None, new),
+            Change::Modified { old, new } => (Some(old), new),
+            Change::Removed(_) => continue,
+        };
+
+        let attributes = new.attributes();
+        if old.map(|old| &old.attributes().policies) != Some(&attributes.policies) {
+            parameter_policy::validate(&attributes.policies, attributes.tier)?;
+        }
+    }
+    Ok(())
+}
+
+// apply_diff makes the parameters under `prefix` look like the "new"
+// side of the diff: added keys are created, modified keys are
+// overwritten and removed keys are deleted.
+pub fn apply_diff(
+    backend: &dyn ParameterBackend,
+    diff: &ParameterDiff,
+    prefix: &str,
+    operation_mode: OperationMode,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut deletions: Vec<String> = Vec::new();
+
+    for (relative_key, change) in diff.changes() {
+        let key = format!("{}{}", prefix, relative_key);
+
+        match change {
+            Change::Added(param) => {
+                if operation_mode == OperationMode::ReadWrite {
+                    eprintln!("Creating key {}...", key);
+                    backend.put_parameter(&key, param, false)?;
+                } else {
+                    eprintln!("[DRY-RUN] Would create key {}...", key);
+                }
+            }
+            Change::Modified { new, .. } => {
+                if operation_mode == OperationMode::ReadWrite {
+                    eprintln!("Updating key {}...", key);
+                    backend.put_parameter(&key, new, true)?;
+                } else {
+                    eprintln!("[DRY-RUN] Would update key {}...", key);
+                }
+            }
+            Change::Removed(_) => deletions.push(key),
+        }
+    }
+
+    if !deletions.is_empty() {
+        delete_parameters(backend, deletions, operation_mode)?;
+    }
     Ok(())
 }
@@ -0,0 +1,364 @@
+use std::error;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::aws::parameter_store::{Parameter, ParameterCollection, ParameterError, ParameterType};
+
+/// The plaintext formats keez can read parameters from, besides its
+/// own export files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputFormat {
+    /// NAME=value lines, as in a .env file.
+    Dotenv,
+    /// A JSON object, where nested objects become deeper paths.
+    Json,
+    /// The JSON printed by `aws ssm get-parameters-by-path`,
+    /// `get-parameters` or `get-parameter`.
+    AwsCli,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dotenv" => Ok(InputFormat::Dotenv),
+            "json" => Ok(InputFormat::Json),
+            "aws-cli" => Ok(InputFormat::AwsCli),
+            _ => Err(format!(
+                "unknown input format {:?}, expected dotenv, json or aws-cli",
+                s
+            )),
+        }
+    }
+}
+
+impl InputFormat {
+    /// Whether the format carries full parameter names, rather than
+    /// names which need a prefix to put them under.
+    pub fn has_full_names(self) -> bool {
+        self == InputFormat::AwsCli
+    }
+}
+
+/// The format of the plaintext file at `path`: `format` if given,
+/// otherwise guessed from the contents.
+pub fn detect_format(
+    path: &Path,
+    format: Option<InputFormat>,
+) -> Result<InputFormat, Box<dyn error::Error>> {
+    match format {
+        Some(format) => Ok(format),
+        None => Ok(guess_format(&fs::read_to_string(path)?)),
+    }
+}
+
+/// Read parameters from a plaintext file.  If no format is given, it's
+/// guessed from the contents.  See `parse`.
+pub fn read(
+    path: &Path,
+    format: Option<InputFormat>,
+    default_type: ParameterType,
+) -> Result<ParameterCollection, Box<dyn error::Error>> {
+    let text = fs::read_to_string(path)?;
+    let format = format.unwrap_or_else(|| guess_format(&text));
+    parse(&text, format, default_type)
+}
+
+// Anything which isn't JSON is assumed to be a dotenv file.
+fn guess_format(text: &str) -> InputFormat {
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(object))
+            if object.contains_key("Parameters") || object.contains_key("Parameter") =>
+        {
+            InputFormat::AwsCli
+        }
+        Ok(_) => InputFormat::Json,
+        Err(_) => InputFormat::Dotenv,
+    }
+}
+
+/// Parse parameters in the given format.  Dotenv and JSON input has no
+/// notion of where the parameters live, so they're returned with an
+/// empty prefix and keys like /NAME, ready to be rerooted, and they all
+/// get `default_type`.  AWS CLI output carries full names and types;
+/// its prefix is the deepest path all the names share.
+pub fn parse(
+    text: &str,
+    format: InputFormat,
+    default_type: ParameterType,
+) -> Result<ParameterCollection, Box<dyn error::Error>> {
+    match format {
+        InputFormat::Dotenv => {
+            let mut collection = ParameterCollection::new(String::new());
+            for (name, value) in parse_dotenv(text)? {
+                collection.insert(
+                    format!("/{}", name),
+                    Parameter::new(value, default_type.to_string()),
+                );
+            }
+            Ok(collection)
+        }
+        InputFormat::Json => {
+            let mut collection = ParameterCollection::new(String::new());
+            flatten_json(
+                &serde_json::from_str(text)?,
+                "",
+                default_type,
+                &mut collection,
+            )?;
+            Ok(collection)
+        }
+        InputFormat::AwsCli => parse_aws_cli(&serde_json::from_str(text)?),
+    }
+}
+
+fn invalid(description: String) -> Box<dyn error::Error> {
+    ParameterError::InvalidInput(description).into()
+}
+
+// Parse a .env file the way the common dotenv libraries do: blank
+// lines and # comments are skipped, an `export ` in front is allowed,
+// single-quoted values are taken literally, double-quoted values
+// understand backslash escapes, and both may span several lines.
+fn parse_dotenv(text: &str) -> Result<Vec<(String, String)>, Box<dyn error::Error>> {
+    let mut variables = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line_number = 1;
+
+    loop {
+        // Skip whitespace and comments up to the next assignment.
+        match chars.peek() {
+            None => break,
+            Some('\n') => {
+                line_number += 1;
+                chars.next();
+                continue;
+            }
+            Some(c) if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            Some('#') => {
+                while matches!(chars.peek(), Some(c) if *c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        let mut name = String::new();
+        while let Some(c) = chars.peek() {
+            if *c == '=' || *c == '\n' {
+                break;
+            }
+            name.push(*c);
+            chars.next();
+        }
+        if chars.next() != Some('=') {
+            return Err(invalid(format!(
+                "line {} of the dotenv input has no '='",
+                line_number
+            )));
+        }
+
+        let name = name.trim();
+        let name = name.strip_prefix("export ").unwrap_or(name).trim();
+        if name.is_empty() {
+            return Err(invalid(format!(
+                "line {} of the dotenv input has no name",
+                line_number
+            )));
+        }
+
+        let mut value = String::new();
+        match chars.peek() {
+            Some('\'') => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line_number += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => {
+                            return Err(invalid(format!("unterminated quote in {}", name)));
+                        }
+                    }
+                }
+            }
+            Some('"') => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some('r') => value.push('\r'),
+                            Some('t') => value.push('\t'),
+                            Some(c) => value.push(c),
+                            None => {
+                                return Err(invalid(format!("unterminated quote in {}", name)));
+                            }
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line_number += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => {
+                            return Err(invalid(format!("unterminated quote in {}", name)));
+                        }
+                    }
+                }
+            }
+            _ => {
+                while let Some(c) = chars.peek() {
+                    if *c == '\n' {
+                        break;
+                    }
+                    value.push(*c);
+                    chars.next();
+                }
+                // An unquoted value ends at a comment.
+                if let Some(index) = value.find(" #") {
+                    value.truncate(index);
+                }
+                value = value.trim().to_string();
+            }
+        }
+
+        // Skip whatever follows a closing quote, like a comment.
+        while matches!(chars.peek(), Some(c) if *c != '\n') {
+            chars.next();
+        }
+
+        variables.push((name.to_string(), value));
+    }
+
+    Ok(variables)
+}
+
+// Turn a (possibly nested) JSON object into parameters: nested objects
+// become deeper paths, arrays become StringLists, and other scalars are
+// stored as their text.
+fn flatten_json(
+    value: &Value,
+    path: &str,
+    default_type: ParameterType,
+    collection: &mut ParameterCollection,
+) -> Result<(), Box<dyn error::Error>> {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                flatten_json(
+                    child,
+                    &format!("{}/{}", path, key),
+                    default_type,
+                    collection,
+                )?;
+            }
+        }
+        _ if path.is_empty() => {
+            return Err(invalid("the JSON input must be an object".to_string()));
+        }
+        Value::Array(items) => {
+            let items: Result<Vec<String>, Box<dyn error::Error>> =
+                items.iter().map(|item| json_scalar(item, path)).collect();
+            collection.insert(
+                path.to_string(),
+                Parameter::new(items?.join(","), ParameterType::StringList.to_string()),
+            );
+        }
+        _ => {
+            collection.insert(
+                path.to_string(),
+                Parameter::new(json_scalar(value, path)?, default_type.to_string()),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn json_scalar(value: &Value, path: &str) -> Result<String, Box<dyn error::Error>> {
+    match value {
+        Value::String(text) => Ok(text.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(flag) => Ok(flag.to_string()),
+        _ => Err(invalid(format!(
+            "unsupported value for {}, expected text, a number or true/false",
+            path
+        ))),
+    }
+}
+
+// Read the output of `aws ssm get-parameters-by-path` (or
+// `get-parameters`, or `get-parameter`).  Remember to pass
+// --with-decryption, or SecureString values will be ciphertext.
+fn parse_aws_cli(dump: &Value) -> Result<ParameterCollection, Box<dyn error::Error>> {
+    let entries: Vec<&Value> = match (dump.get("Parameters"), dump.get("Parameter")) {
+        (Some(Value::Array(entries)), _) => entries.iter().collect(),
+        (_, Some(entry)) => vec![entry],
+        _ => {
+            return Err(invalid(
+                "expected the output of `aws ssm get-parameters-by-path`".to_string(),
+            ))
+        }
+    };
+
+    let mut parameters: Vec<(String, Parameter)> = Vec::new();
+    for entry in entries {
+        let field = |name: &str| entry.get(name).and_then(Value::as_str);
+        let name = field("Name").ok_or_else(|| invalid("a parameter has no Name".to_string()))?;
+        let value =
+            field("Value").ok_or_else(|| invalid(format!("parameter {} has no Value", name)))?;
+        let parameter_type = ParameterType::from_str(field("Type").unwrap_or("String"))?;
+
+        parameters.push((
+            name.to_string(),
+            Parameter::new(value.to_string(), parameter_type.to_string()),
+        ));
+    }
+
+    let mut collection = ParameterCollection::new(common_parent(
+        parameters.iter().map(|(name, _)| name.as_str()),
+    ));
+    for (name, parameter) in parameters {
+        collection.insert(name, parameter);
+    }
+    Ok(collection)
+}
+
+// The deepest path which is a parent of all the given names, e.g.
+// /app for /app/db/host and /app/name.
+fn common_parent<'a>(mut names: impl Iterator<Item = &'a str>) -> String {
+    let parent = |name: &'a str| -> Vec<&'a str> {
+        let mut segments: Vec<&str> = name.split('/').collect();
+        segments.pop();
+        segments
+    };
+
+    let mut common = match names.next() {
+        Some(name) => parent(name),
+        None => return String::new(),
+    };
+    for name in names {
+        let segments = parent(name);
+        let shared = common
+            .iter()
+            .zip(segments.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        common.truncate(shared);
+    }
+
+    common.join("/")
+}
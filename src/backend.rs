@@ -0,0 +1,4 @@
+pub mod file_backend;
+pub mod parameter_backend;
+pub mod ssm_backend;
+pub mod tag_filter;
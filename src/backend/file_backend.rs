@@ -0,0 +1,297 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::aws::parameter_store::{Parameter, ParameterError, ParameterVersion};
+use crate::backend::parameter_backend::{ParameterBackend, StoreLocation};
+
+/// FileBackend keeps parameters in memory and writes them back to a
+/// plaintext YAML file after every modification.  It mimics the bits
+/// of Parameter Store semantics that keez relies on (hierarchical
+/// paths, refusing to overwrite, version history), so that commands
+/// can be exercised locally without an AWS account.
+pub struct FileBackend {
+    path: PathBuf,
+    store: RefCell<FileStore>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FileStore {
+    // Every parameter maps to its list of versions, oldest first.
+    #[serde(default)]
+    parameters: BTreeMap<String, Vec<ParameterVersion>>,
+}
+
+impl FileBackend {
+    /// Load the store at `path`, or start with an empty one if the file
+    /// doesn't exist yet.
+    pub fn open(path: PathBuf) -> Result<FileBackend, Box<dyn error::Error>> {
+        let store = if path.exists() {
+            serde_yaml::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            FileStore::default()
+        };
+
+        return Ok(FileBackend {
+            path,
+            store: RefCell::new(store),
+        });
+    }
+
+    fn save(&self) -> Result<(), Box<dyn error::Error>> {
+        let yaml_blob = serde_yaml::to_string(&*self.store.borrow())?;
+        fs::write(&self.path, yaml_blob)?;
+        Ok(())
+    }
+}
+
+impl ParameterBackend for FileBackend {
+    fn parameters_by_path(
+        &self,
+        path_prefix: &str,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>> {
+        self.list_parameters(path_prefix, true)
+    }
+
+    fn list_parameters(
+        &self,
+        path_prefix: &str,
+        recursive: bool,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>> {
+        // Like Parameter Store, only match whole path components:
+        // /foo matches /foo/bar but not /foobar.
+        let hierarchy = format!("{}/", path_prefix.trim_end_matches('/'));
+        let mut result = HashMap::new();
+
+        for (name, versions) in &self.store.borrow().parameters {
+            let relative = match name.strip_prefix(&hierarchy) {
+                Some(relative) => relative,
+                None => continue,
+            };
+            if !recursive && relative.contains('/') {
+                continue;
+            }
+            if let Some(latest) = versions.last() {
+                result.insert(name.clone(), latest.to_parameter());
+            }
+        }
+
+        return Ok(result);
+    }
+
+    fn get_parameter(&self, name: &str) -> Result<Option<Parameter>, Box<dyn error::Error>> {
+        let store = self.store.borrow();
+        return Ok(store
+            .parameters
+            .get(name)
+            .and_then(|versions| versions.last())
+            .map(|latest| latest.to_parameter()));
+    }
+
+    fn put_parameter(
+        &self,
+        name: &str,
+        parameter: &Parameter,
+        overwrite: bool,
+    ) -> Result<(), Box<dyn error::Error>> {
+        {
+            let mut store = self.store.borrow_mut();
+            let versions = store.parameters.entry(name.to_string()).or_default();
+
+            if !versions.is_empty() && !overwrite {
+                return Err(ParameterError::ParameterAlreadyExists(name.to_string()).into());
+            }
+
+            let next_version = versions.last().map_or(1, |latest| latest.version() + 1);
+            versions.push(ParameterVersion::new(
+                next_version,
+                parameter.without_store_metadata(),
+                Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64()),
+                env::var("USER").ok(),
+                Vec::new(),
+            ));
+        }
+
+        self.save()
+    }
+
+    fn delete_parameters(&self, names: &[String]) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let mut deleted: Vec<String> = Vec::new();
+        {
+            let mut store = self.store.borrow_mut();
+            for name in names {
+                if store.parameters.remove(name).is_some() {
+                    deleted.push(name.clone());
+                }
+            }
+        }
+
+        self.save()?;
+        return Ok(deleted);
+    }
+
+    fn location(&self) -> StoreLocation {
+        StoreLocation::default()
+    }
+
+    fn parameter_history(
+        &self,
+        name: &str,
+    ) -> Result<Vec<ParameterVersion>, Box<dyn error::Error>> {
+        let store = self.store.borrow();
+        return Ok(store.parameters.get(name).cloned().unwrap_or_default());
+    }
+
+    fn label_parameter_version(
+        &self,
+        name: &str,
+        version: Option<i64>,
+        labels: &[String],
+    ) -> Result<(), Box<dyn error::Error>> {
+        {
+            let mut store = self.store.borrow_mut();
+            let versions = match store.parameters.get_mut(name) {
+                Some(versions) => versions,
+                None => return Err(format!("parameter {} not found", name).into()),
+            };
+
+            let target = match version {
+                Some(number) => versions.iter().position(|v| *v.version() == number),
+                None => versions.len().checked_sub(1),
+            };
+            let target = match target {
+                Some(index) => index,
+                None => {
+                    return Err(format!(
+                        "parameter {} has no version {}",
+                        name,
+                        version.unwrap_or_default()
+                    )
+                    .into())
+                }
+            };
+
+            // Like Parameter Store, move the labels away from any
+            // other version carrying them.
+            for existing in versions.iter_mut() {
+                existing.labels_mut().retain(|l| !labels.contains(l));
+            }
+            for label in labels {
+                versions[target].labels_mut().push(label.clone());
+            }
+        }
+
+        self.save()
+    }
+
+    fn unlabel_parameter_version(
+        &self,
+        name: &str,
+        version: i64,
+        labels: &[String],
+    ) -> Result<(), Box<dyn error::Error>> {
+        {
+            let mut store = self.store.borrow_mut();
+            let versions = store.parameters.get_mut(name).into_iter().flatten();
+
+            for existing in versions.filter(|v| *v.version() == version) {
+                existing.labels_mut().retain(|l| !labels.contains(l));
+            }
+        }
+
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mktemp::Temp;
+
+    use crate::aws::parameter_store;
+    use crate::aws::parameter_store::{ParameterAttributes, ParameterTier, ParameterType};
+    use crate::flags::operation_mode::OperationMode;
+
+    // Copy /app/dev to /app/prod within the store at `path`, the way
+    // `keez copy` does.
+    fn copy_in_store(path: &Temp) -> Result<(), Box<dyn error::Error>> {
+        let backend = FileBackend::open(path.to_path_buf())?;
+        let source = parameter_store::get_parameters_by_path(&backend, "/app/dev".to_string())?;
+        let rerooted = parameter_store::reroot_parameters(source, "/app/prod".to_string())?;
+        parameter_store::push_new_parameters(&backend, rerooted, OperationMode::ReadWrite)
+    }
+
+    fn host_attributes() -> ParameterAttributes {
+        let mut tags = BTreeMap::new();
+        tags.insert("team".to_string(), "example".to_string());
+        ParameterAttributes {
+            description: Some("example description".to_string()),
+            tier: Some(ParameterTier::Advanced),
+            tags,
+            ..ParameterAttributes::default()
+        }
+    }
+
+    fn seed_store(path: &Temp) -> FileBackend {
+        let backend = FileBackend::open(path.to_path_buf()).unwrap();
+        let host = Parameter::new("db.example".to_string(), "String".to_string())
+            .with_attributes(host_attributes());
+        let password = Parameter::new("example-password".to_string(), "SecureString".to_string());
+        backend
+            .put_parameter("/app/dev/db/host", &host, false)
+            .unwrap();
+        backend
+            .put_parameter("/app/dev/password", &password, false)
+            .unwrap();
+        backend
+    }
+
+    #[test]
+    fn copy_round_trips_values_and_attributes() {
+        let path = Temp::new_path();
+        seed_store(&path);
+
+        copy_in_store(&path).unwrap();
+
+        let reopened = FileBackend::open(path.to_path_buf()).unwrap();
+        let copied = reopened.parameters_by_path("/app/prod").unwrap();
+        assert_eq!(copied.len(), 2);
+
+        let host = &copied["/app/prod/db/host"];
+        assert_eq!(*host.value(), "db.example");
+        assert_eq!(*host.parameter_type(), ParameterType::String);
+        assert_eq!(*host.attributes(), host_attributes());
+
+        let password = &copied["/app/prod/password"];
+        assert_eq!(*password.value(), "example-password");
+        assert_eq!(*password.parameter_type(), ParameterType::SecureString);
+
+        assert_eq!(reopened.parameters_by_path("/app/dev").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn copy_refuses_to_overwrite() {
+        let path = Temp::new_path();
+        let backend = seed_store(&path);
+        let existing = Parameter::new("kept".to_string(), "String".to_string());
+        backend
+            .put_parameter("/app/prod/password", &existing, false)
+            .unwrap();
+
+        assert!(copy_in_store(&path).is_err());
+
+        let reopened = FileBackend::open(path.to_path_buf()).unwrap();
+        let kept = reopened
+            .get_parameter("/app/prod/password")
+            .unwrap()
+            .unwrap();
+        assert_eq!(*kept.value(), "kept");
+    }
+}
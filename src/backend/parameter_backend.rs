@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::error;
+
+use crate::aws::parameter_store::{Parameter, ParameterVersion};
+
+/// Where a backend keeps its parameters, as far as it can tell.
+#[derive(Clone, Debug, Default)]
+pub struct StoreLocation {
+    pub account: Option<String>,
+    pub region: Option<String>,
+}
+
+/// A ParameterBackend is anything keez can read parameters from and
+/// write parameters to.  The real thing is AWS Systems Manager
+/// Parameter Store, but the commands only ever talk to this trait, so
+/// that they can just as well run against a local store.
+pub trait ParameterBackend {
+    /// Recursively fetch all parameters (with decrypted values) whose
+    /// names live underneath the given path prefix.
+    fn parameters_by_path(
+        &self,
+        path_prefix: &str,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>>;
+
+    /// Like `parameters_by_path`, but for listing what exists rather
+    /// than reading values: SecureString values may be left encrypted,
+    /// and with `recursive` unset only the parameters directly under
+    /// the path are returned.
+    fn list_parameters(
+        &self,
+        path_prefix: &str,
+        recursive: bool,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>>;
+
+    /// Fetch a single parameter by its full name, or `None` if it
+    /// doesn't exist.
+    fn get_parameter(&self, name: &str) -> Result<Option<Parameter>, Box<dyn error::Error>>;
+
+    /// Store a parameter.  If `overwrite` is false and the parameter
+    /// already exists, this fails with
+    /// `ParameterError::ParameterAlreadyExists`.
+    fn put_parameter(
+        &self,
+        name: &str,
+        parameter: &Parameter,
+        overwrite: bool,
+    ) -> Result<(), Box<dyn error::Error>>;
+
+    /// Delete the named parameters, returning the names which were
+    /// actually deleted.  Names which don't exist are skipped.
+    fn delete_parameters(&self, names: &[String]) -> Result<Vec<String>, Box<dyn error::Error>>;
+
+    /// Describe where the parameters live, for the record.
+    fn location(&self) -> StoreLocation;
+
+    /// Fetch all known versions of a parameter, oldest first.  A
+    /// parameter which doesn't exist has no versions.
+    fn parameter_history(&self, name: &str)
+        -> Result<Vec<ParameterVersion>, Box<dyn error::Error>>;
+
+    /// Attach labels to a version of a parameter, or to its latest
+    /// version if `version` is `None`.  A label can only be on one
+    /// version of a parameter at a time, so it moves away from any
+    /// other version which carried it.
+    fn label_parameter_version(
+        &self,
+        name: &str,
+        version: Option<i64>,
+        labels: &[String],
+    ) -> Result<(), Box<dyn error::Error>>;
+
+    /// Whether `unlabel_parameter_version` works with this backend.
+    fn can_remove_labels(&self) -> bool {
+        true
+    }
+
+    /// Remove labels from a version of a parameter.  Labels which
+    /// aren't on that version are ignored.
+    fn unlabel_parameter_version(
+        &self,
+        name: &str,
+        version: i64,
+        labels: &[String],
+    ) -> Result<(), Box<dyn error::Error>>;
+}
@@ -0,0 +1,667 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::future::Future;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use rand::{thread_rng, Rng};
+use rusoto_core::credential::ProfileProvider;
+use rusoto_core::{HttpClient, Region, RusotoError};
+use rusoto_ssm::{
+    AddTagsToResourceRequest, DeleteParametersRequest, DescribeParametersRequest,
+    GetParameterError, GetParameterHistoryError, GetParameterHistoryRequest, GetParameterRequest,
+    GetParametersByPathRequest, LabelParameterVersionRequest, ListTagsForResourceRequest,
+    ParameterInlinePolicy, ParameterMetadata, ParameterStringFilter, PutParameterError,
+    PutParameterRequest, RemoveTagsFromResourceRequest, Ssm, SsmClient, Tag,
+};
+use rusoto_sts::{GetCallerIdentityRequest, Sts, StsClient};
+use tokio::runtime;
+
+use crate::aws::parameter_policy::{self, ParameterPolicy};
+use crate::aws::parameter_store::{
+    Parameter, ParameterAttributes, ParameterError, ParameterTier, ParameterType, ParameterVersion,
+};
+use crate::backend::parameter_backend::{ParameterBackend, StoreLocation};
+
+// The DeleteParameters API accepts at most this many names per call.
+const DELETE_BATCH_SIZE: usize = 10;
+
+// The resource type to pass to the tagging APIs.
+const TAG_RESOURCE_TYPE: &str = "Parameter";
+
+// How often to try a throttled request, and how long to wait before
+// the first retry.  The wait doubles for each further retry.
+const MAX_ATTEMPTS: u32 = 6;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Where and as whom to connect to Parameter Store.  Anything left
+/// unset falls back to the usual AWS environment variables and
+/// configuration files.
+#[derive(Clone, Debug, Default)]
+pub struct SsmConnection {
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    pub endpoint_url: Option<String>,
+}
+
+impl SsmConnection {
+    /// Return a copy of this connection where the given region and
+    /// profile, if any, take precedence.
+    pub fn with_overrides(
+        &self,
+        region: &Option<String>,
+        profile: &Option<String>,
+    ) -> SsmConnection {
+        SsmConnection {
+            region: region.clone().or_else(|| self.region.clone()),
+            profile: profile.clone().or_else(|| self.profile.clone()),
+            endpoint_url: self.endpoint_url.clone(),
+        }
+    }
+
+    fn region(&self) -> Result<Region, Box<dyn error::Error>> {
+        let region = match &self.region {
+            Some(name) => Region::from_str(name)?,
+            None => Region::default(),
+        };
+
+        // A custom endpoint still needs a region name for request
+        // signing, so keep whichever one we would have used anyway.
+        match &self.endpoint_url {
+            Some(endpoint) => Ok(Region::Custom {
+                name: region.name().to_string(),
+                endpoint: endpoint.clone(),
+            }),
+            None => Ok(region),
+        }
+    }
+
+    fn client(&self) -> Result<SsmClient, Box<dyn error::Error>> {
+        let region = self.region()?;
+
+        match &self.profile {
+            Some(profile) => {
+                let mut provider = ProfileProvider::new()?;
+                provider.set_profile(profile.clone());
+                Ok(SsmClient::new_with(HttpClient::new()?, provider, region))
+            }
+            None => Ok(SsmClient::new(region)),
+        }
+    }
+
+    // Only used to find out which account we're talking to.
+    fn sts_client(&self) -> Result<StsClient, Box<dyn error::Error>> {
+        let region = self.region()?;
+
+        match &self.profile {
+            Some(profile) => {
+                let mut provider = ProfileProvider::new()?;
+                provider.set_profile(profile.clone());
+                Ok(StsClient::new_with(HttpClient::new()?, provider, region))
+            }
+            None => Ok(StsClient::new(region)),
+        }
+    }
+}
+
+/// SsmBackend talks to the real AWS Systems Manager Parameter Store.
+pub struct SsmBackend {
+    client: SsmClient,
+    sts_client: StsClient,
+    region_name: String,
+    runtime: RefCell<runtime::Runtime>,
+    // Listing tags takes a request per parameter, so it's skipped for
+    // commands which don't need them.  Parameters then come back
+    // without tags, and writing them leaves the stored tags alone.
+    fetch_tags: bool,
+    debug: bool,
+}
+
+impl SsmBackend {
+    pub fn new(
+        connection: &SsmConnection,
+        fetch_tags: bool,
+        debug: bool,
+    ) -> Result<SsmBackend, Box<dyn error::Error>> {
+        let runtime = runtime::Builder::new()
+            .threaded_scheduler()
+            .enable_all()
+            .build()?;
+
+        return Ok(SsmBackend {
+            client: connection.client()?,
+            sts_client: connection.sts_client()?,
+            region_name: connection.region()?.name().to_string(),
+            runtime: RefCell::new(runtime),
+            fetch_tags,
+            debug,
+        });
+    }
+
+    // Super awful, let's chuck out all the lovingly-crafted futures
+    // work and just run the requests in a blocking fashion on the
+    // main thread.
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.borrow_mut().block_on(future)
+    }
+
+    // Run a request, retrying with exponential backoff while AWS
+    // throttles us.  Fetching many parameters, or their tags, easily
+    // runs into the API rate limits.
+    // The error is rusoto's, so it's as large as it is.
+    #[allow(clippy::result_large_err)]
+    fn send<T, E, F, Fut>(&self, request: F) -> Result<T, RusotoError<E>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, RusotoError<E>>>,
+    {
+        let mut delay = INITIAL_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let result = self.block_on(request());
+            match &result {
+                Err(e) if attempt < MAX_ATTEMPTS && is_throttling(e) => {
+                    let jitter = Duration::from_millis(thread_rng().gen_range(0, 100));
+                    if self.debug {
+                        eprintln!("Throttled by AWS, retrying in {:?}.", delay + jitter);
+                    }
+                    thread::sleep(delay + jitter);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    fn raw_parameters_by_path(
+        &self,
+        path_prefix: &str,
+        recursive: bool,
+        with_decryption: bool,
+    ) -> Result<Vec<rusoto_ssm::Parameter>, Box<dyn error::Error>> {
+        let mut req = GetParametersByPathRequest {
+            path: path_prefix.to_owned(),
+            with_decryption: Some(with_decryption),
+            recursive: Some(recursive),
+            ..GetParametersByPathRequest::default()
+        };
+
+        let mut res = self.send(|| self.client.get_parameters_by_path(req.clone()))?;
+
+        let mut parameters: Vec<rusoto_ssm::Parameter> = Vec::new();
+        if let Some(new_params) = res.parameters {
+            parameters.extend(new_params.into_iter());
+        }
+
+        // Get next set of parameters if there's a next_token.
+        while let Some(next_token) = res.next_token {
+            req.next_token = Some(next_token);
+            res = self.send(|| self.client.get_parameters_by_path(req.clone()))?;
+
+            if let Some(new_params) = res.parameters {
+                parameters.extend(new_params.into_iter());
+            }
+        }
+
+        if self.debug {
+            eprintln!("raw_parameters_by_path: received from API:");
+            eprintln!("{:?}", parameters);
+        }
+
+        return Ok(parameters);
+    }
+
+    // GetParametersByPath and GetParameter only return values, so the
+    // rest of a parameter's settings have to come from
+    // DescribeParameters.  The result is keyed by parameter name.
+    fn describe_parameters(
+        &self,
+        filter: ParameterStringFilter,
+    ) -> Result<HashMap<String, ParameterMetadata>, Box<dyn error::Error>> {
+        let mut req = DescribeParametersRequest {
+            parameter_filters: Some(vec![filter]),
+            ..DescribeParametersRequest::default()
+        };
+
+        let mut metadata: HashMap<String, ParameterMetadata> = HashMap::new();
+        loop {
+            let res = self.send(|| self.client.describe_parameters(req.clone()))?;
+
+            for entry in res.parameters.unwrap_or_default() {
+                if let Some(name) = entry.name.clone() {
+                    metadata.insert(name, entry);
+                }
+            }
+
+            match res.next_token {
+                Some(next_token) => req.next_token = Some(next_token),
+                None => break,
+            }
+        }
+
+        if self.debug {
+            eprintln!("describe_parameters: received from API:");
+            eprintln!("{:?}", metadata);
+        }
+
+        return Ok(metadata);
+    }
+
+    // Fetch the parameters under a path along with their settings and
+    // tags, which takes a few different API calls.
+    fn fetch_by_path(
+        &self,
+        path_prefix: &str,
+        recursive: bool,
+        with_decryption: bool,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>> {
+        let raw_parameters =
+            self.raw_parameters_by_path(path_prefix, recursive, with_decryption)?;
+        let metadata = self.describe_parameters(ParameterStringFilter {
+            key: "Path".to_string(),
+            option: Some(if recursive { "Recursive" } else { "OneLevel" }.to_string()),
+            values: Some(vec![path_prefix.to_string()]),
+        })?;
+        let mut result = HashMap::new();
+
+        for raw_param in raw_parameters {
+            let name = raw_param.name.clone().unwrap();
+            let tags = self.tags_if_wanted(&name)?;
+            let parameter = from_raw_parameter(raw_param, metadata.get(&name), tags);
+            result.insert(name, parameter);
+        }
+
+        return Ok(result);
+    }
+
+    fn list_tags(&self, name: &str) -> Result<BTreeMap<String, String>, Box<dyn error::Error>> {
+        let req = ListTagsForResourceRequest {
+            resource_id: name.to_string(),
+            resource_type: TAG_RESOURCE_TYPE.to_string(),
+        };
+        let res = self.send(|| self.client.list_tags_for_resource(req.clone()))?;
+
+        return Ok(res
+            .tag_list
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tag| (tag.key, tag.value))
+            .collect());
+    }
+
+    fn tags_if_wanted(
+        &self,
+        name: &str,
+    ) -> Result<BTreeMap<String, String>, Box<dyn error::Error>> {
+        if self.fetch_tags {
+            self.list_tags(name)
+        } else {
+            Ok(BTreeMap::new())
+        }
+    }
+
+    // Make the tags on an existing parameter match `tags`, touching
+    // only the tags which actually differ.
+    fn sync_tags(
+        &self,
+        name: &str,
+        tags: &BTreeMap<String, String>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let current = self.list_tags(name)?;
+
+        let stale: Vec<String> = current
+            .keys()
+            .filter(|key| !tags.contains_key(*key))
+            .cloned()
+            .collect();
+        let changed: Vec<Tag> = tags
+            .iter()
+            .filter(|(key, value)| current.get(*key) != Some(*value))
+            .map(|(key, value)| Tag {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect();
+
+        if !stale.is_empty() {
+            let req = RemoveTagsFromResourceRequest {
+                resource_id: name.to_string(),
+                resource_type: TAG_RESOURCE_TYPE.to_string(),
+                tag_keys: stale,
+            };
+            self.send(|| self.client.remove_tags_from_resource(req.clone()))?;
+        }
+
+        if !changed.is_empty() {
+            let req = AddTagsToResourceRequest {
+                resource_id: name.to_string(),
+                resource_type: TAG_RESOURCE_TYPE.to_string(),
+                tags: changed,
+            };
+            self.send(|| self.client.add_tags_to_resource(req.clone()))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ParameterBackend for SsmBackend {
+    fn parameters_by_path(
+        &self,
+        path_prefix: &str,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>> {
+        self.fetch_by_path(path_prefix, true, true)
+    }
+
+    fn list_parameters(
+        &self,
+        path_prefix: &str,
+        recursive: bool,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>> {
+        self.fetch_by_path(path_prefix, recursive, false)
+    }
+
+    fn get_parameter(&self, name: &str) -> Result<Option<Parameter>, Box<dyn error::Error>> {
+        let req = GetParameterRequest {
+            name: name.to_string(),
+            with_decryption: Some(true),
+        };
+
+        let raw_param = match self.send(|| self.client.get_parameter(req.clone())) {
+            Ok(res) => res.parameter,
+            Err(RusotoError::Service(GetParameterError::ParameterNotFound(_))) => None,
+            Err(other) => return Err(other.into()),
+        };
+
+        match raw_param {
+            Some(raw_param) => {
+                let metadata = self.describe_parameters(ParameterStringFilter {
+                    key: "Name".to_string(),
+                    option: Some("Equals".to_string()),
+                    values: Some(vec![name.to_string()]),
+                })?;
+                let tags = self.tags_if_wanted(name)?;
+                Ok(Some(from_raw_parameter(
+                    raw_param,
+                    metadata.get(name),
+                    tags,
+                )))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn put_parameter(
+        &self,
+        name: &str,
+        parameter: &Parameter,
+        overwrite: bool,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let attributes = parameter.attributes();
+
+        // Parameter Store refuses a KMS key for anything but a
+        // SecureString, which would make changing the type awkward.
+        let key_id = match parameter.parameter_type() {
+            ParameterType::SecureString => attributes.key_id.clone(),
+            _ => None,
+        };
+
+        // Tags can only be passed along when creating a parameter;
+        // existing ones have theirs updated separately.
+        let tags: Vec<Tag> = attributes
+            .tags
+            .iter()
+            .map(|(key, value)| Tag {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect();
+
+        // Leaving policies out keeps the stored ones, so clear them
+        // explicitly when overwriting a parameter which could have some.
+        let policies = if !attributes.policies.is_empty() {
+            Some(parameter_policy::to_aws_json(&attributes.policies)?)
+        } else if overwrite
+            && matches!(
+                attributes.tier,
+                Some(ParameterTier::Advanced) | Some(ParameterTier::IntelligentTiering)
+            )
+        {
+            Some("[]".to_string())
+        } else {
+            None
+        };
+
+        let req = PutParameterRequest {
+            allowed_pattern: attributes.allowed_pattern.clone(),
+            data_type: attributes.data_type.clone(),
+            description: attributes.description.clone(),
+            key_id,
+            name: name.to_string(),
+            policies,
+            tier: attributes.tier.map(|tier| tier.to_string()),
+            type_: Some(parameter.parameter_type().to_string()),
+            value: parameter.value().clone(),
+            overwrite: Some(overwrite),
+            tags: if overwrite || tags.is_empty() {
+                None
+            } else {
+                Some(tags)
+            },
+            ..PutParameterRequest::default()
+        };
+
+        match self.send(|| self.client.put_parameter(req.clone())) {
+            Ok(_) if overwrite && self.fetch_tags => self.sync_tags(name, &attributes.tags),
+            Ok(_) => Ok(()),
+            Err(RusotoError::Service(PutParameterError::ParameterAlreadyExists(_))) => {
+                Err(ParameterError::ParameterAlreadyExists(name.to_string()).into())
+            }
+            Err(other) => Err(other.into()),
+        }
+    }
+
+    fn delete_parameters(&self, names: &[String]) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let mut deleted: Vec<String> = Vec::new();
+
+        for batch in names.chunks(DELETE_BATCH_SIZE) {
+            let req = DeleteParametersRequest {
+                names: batch.to_vec(),
+            };
+            let res = self.send(|| self.client.delete_parameters(req.clone()))?;
+
+            if let Some(deleted_names) = res.deleted_parameters {
+                deleted.extend(deleted_names.into_iter());
+            }
+        }
+
+        return Ok(deleted);
+    }
+
+    fn location(&self) -> StoreLocation {
+        let identity = self.send(|| {
+            self.sts_client
+                .get_caller_identity(GetCallerIdentityRequest {})
+        });
+
+        // The account is nice to have, so don't fail without it.
+        let account = match identity {
+            Ok(identity) => identity.account,
+            Err(e) => {
+                if self.debug {
+                    eprintln!("Couldn't look up the AWS account: {}", e);
+                }
+                None
+            }
+        };
+
+        StoreLocation {
+            account,
+            region: Some(self.region_name.clone()),
+        }
+    }
+
+    fn parameter_history(
+        &self,
+        name: &str,
+    ) -> Result<Vec<ParameterVersion>, Box<dyn error::Error>> {
+        let mut req = GetParameterHistoryRequest {
+            name: name.to_string(),
+            with_decryption: Some(true),
+            ..GetParameterHistoryRequest::default()
+        };
+
+        let mut versions: Vec<ParameterVersion> = Vec::new();
+        loop {
+            let res = match self.send(|| self.client.get_parameter_history(req.clone())) {
+                Ok(res) => res,
+                Err(RusotoError::Service(GetParameterHistoryError::ParameterNotFound(_))) => {
+                    break;
+                }
+                Err(other) => return Err(other.into()),
+            };
+
+            for raw_version in res.parameters.unwrap_or_default() {
+                versions.push(ParameterVersion::new(
+                    raw_version.version.unwrap_or_default(),
+                    Parameter::new(
+                        raw_version.value.unwrap_or_default(),
+                        raw_version.type_.unwrap_or_default(),
+                    )
+                    .with_attributes(attributes_from_api(
+                        raw_version.description,
+                        raw_version.tier,
+                        raw_version.key_id,
+                        raw_version.allowed_pattern,
+                        raw_version.data_type,
+                        raw_version.policies,
+                    )),
+                    raw_version.last_modified_date,
+                    raw_version.last_modified_user,
+                    raw_version.labels.unwrap_or_default(),
+                ));
+            }
+
+            match res.next_token {
+                Some(next_token) => req.next_token = Some(next_token),
+                None => break,
+            }
+        }
+
+        return Ok(versions);
+    }
+
+    fn label_parameter_version(
+        &self,
+        name: &str,
+        version: Option<i64>,
+        labels: &[String],
+    ) -> Result<(), Box<dyn error::Error>> {
+        let req = LabelParameterVersionRequest {
+            labels: labels.to_vec(),
+            name: name.to_string(),
+            parameter_version: version,
+        };
+        let res = self.send(|| self.client.label_parameter_version(req.clone()))?;
+
+        match res.invalid_labels {
+            Some(invalid) if !invalid.is_empty() => Err(ParameterError::InvalidLabel(format!(
+                "Parameter Store rejected {}",
+                invalid.join(", ")
+            ))
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    // The UnlabelParameterVersion API is newer than the rusoto_ssm
+    // release we build against, so there's no way to call it yet.
+    fn can_remove_labels(&self) -> bool {
+        false
+    }
+
+    fn unlabel_parameter_version(
+        &self,
+        _name: &str,
+        _version: i64,
+        _labels: &[String],
+    ) -> Result<(), Box<dyn error::Error>> {
+        Err(ParameterError::Unsupported(
+            "removing labels from Parameter Store; use the AWS console or CLI instead".to_string(),
+        )
+        .into())
+    }
+}
+
+// Convert a parameter as returned by the SSM API, plus its entry from
+// DescribeParameters if we found one, into our own model.
+fn from_raw_parameter(
+    raw_param: rusoto_ssm::Parameter,
+    metadata: Option<&ParameterMetadata>,
+    tags: BTreeMap<String, String>,
+) -> Parameter {
+    let mut attributes = match metadata {
+        Some(m) => attributes_from_api(
+            m.description.clone(),
+            m.tier.clone(),
+            m.key_id.clone(),
+            m.allowed_pattern.clone(),
+            m.data_type.clone(),
+            m.policies.clone(),
+        ),
+        None => ParameterAttributes::default(),
+    };
+    attributes.tags = tags;
+
+    Parameter::new(
+        raw_param.value.unwrap_or_default(),
+        raw_param.type_.unwrap_or_default(),
+    )
+    .with_attributes(attributes)
+    .with_store_metadata(
+        raw_param.version,
+        raw_param.last_modified_date,
+        metadata.and_then(|m| m.last_modified_user.clone()),
+    )
+}
+
+// Build attributes from what the API reports, dropping values which
+// are simply Parameter Store's defaults, to keep the YAML tidy.  Tags
+// have to be looked up separately.
+fn attributes_from_api(
+    description: Option<String>,
+    tier: Option<String>,
+    key_id: Option<String>,
+    allowed_pattern: Option<String>,
+    data_type: Option<String>,
+    policies: Option<Vec<ParameterInlinePolicy>>,
+) -> ParameterAttributes {
+    ParameterAttributes {
+        description,
+        tier: tier
+            .and_then(|tier| ParameterTier::from_str(&tier).ok())
+            .filter(|tier| *tier != ParameterTier::Standard),
+        key_id: key_id.filter(|key_id| key_id != "alias/aws/ssm"),
+        allowed_pattern,
+        data_type: data_type.filter(|data_type| data_type != "text"),
+        tags: BTreeMap::new(),
+        policies: policies
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|policy| policy.policy_text)
+            .filter_map(|text| ParameterPolicy::from_aws_json(&text).ok())
+            .collect(),
+    }
+}
+
+// Throttling isn't one of the modelled errors of the SSM API, so look
+// for it in the raw response.
+fn is_throttling<E>(error: &RusotoError<E>) -> bool {
+    match error {
+        RusotoError::Unknown(response) => response.body_as_str().contains("ThrottlingException"),
+        _ => false,
+    }
+}
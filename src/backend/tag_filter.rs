@@ -0,0 +1,134 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::str::FromStr;
+
+use crate::aws::parameter_store::{Parameter, ParameterError, ParameterVersion};
+use crate::backend::parameter_backend::{ParameterBackend, StoreLocation};
+
+/// A condition on a parameter's tags: either `key=value`, or just `key`
+/// to match any parameter carrying that tag, whatever its value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TagFilter {
+    key: String,
+    value: Option<String>,
+}
+
+impl TagFilter {
+    pub fn matches(&self, tags: &BTreeMap<String, String>) -> bool {
+        match &self.value {
+            Some(value) => tags.get(&self.key) == Some(value),
+            None => tags.contains_key(&self.key),
+        }
+    }
+}
+
+impl FromStr for TagFilter {
+    type Err = ParameterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = match s.find('=') {
+            Some(index) => (&s[..index], Some(s[index + 1..].to_string())),
+            None => (s, None),
+        };
+
+        if key.is_empty() {
+            return Err(ParameterError::InvalidTagFilter(s.to_string()));
+        }
+
+        Ok(TagFilter {
+            key: key.to_string(),
+            value,
+        })
+    }
+}
+
+/// TagFilteredBackend wraps another backend, and hides any parameters
+/// which don't match all of the tag filters when listing by path.
+/// Everything else is passed straight through.
+pub struct TagFilteredBackend {
+    inner: Box<dyn ParameterBackend>,
+    filters: Vec<TagFilter>,
+}
+
+impl TagFilteredBackend {
+    pub fn new(inner: Box<dyn ParameterBackend>, filters: Vec<TagFilter>) -> TagFilteredBackend {
+        TagFilteredBackend { inner, filters }
+    }
+
+    fn matches(&self, parameter: &Parameter) -> bool {
+        self.filters
+            .iter()
+            .all(|filter| filter.matches(&parameter.attributes().tags))
+    }
+}
+
+impl ParameterBackend for TagFilteredBackend {
+    fn parameters_by_path(
+        &self,
+        path_prefix: &str,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>> {
+        let mut parameters = self.inner.parameters_by_path(path_prefix)?;
+        parameters.retain(|_name, param| self.matches(param));
+        Ok(parameters)
+    }
+
+    fn list_parameters(
+        &self,
+        path_prefix: &str,
+        recursive: bool,
+    ) -> Result<HashMap<String, Parameter>, Box<dyn error::Error>> {
+        let mut parameters = self.inner.list_parameters(path_prefix, recursive)?;
+        parameters.retain(|_name, param| self.matches(param));
+        Ok(parameters)
+    }
+
+    fn get_parameter(&self, name: &str) -> Result<Option<Parameter>, Box<dyn error::Error>> {
+        self.inner.get_parameter(name)
+    }
+
+    fn put_parameter(
+        &self,
+        name: &str,
+        parameter: &Parameter,
+        overwrite: bool,
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.inner.put_parameter(name, parameter, overwrite)
+    }
+
+    fn delete_parameters(&self, names: &[String]) -> Result<Vec<String>, Box<dyn error::Error>> {
+        self.inner.delete_parameters(names)
+    }
+
+    fn location(&self) -> StoreLocation {
+        self.inner.location()
+    }
+
+    fn parameter_history(
+        &self,
+        name: &str,
+    ) -> Result<Vec<ParameterVersion>, Box<dyn error::Error>> {
+        self.inner.parameter_history(name)
+    }
+
+    fn label_parameter_version(
+        &self,
+        name: &str,
+        version: Option<i64>,
+        labels: &[String],
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.inner.label_parameter_version(name, version, labels)
+    }
+
+    fn can_remove_labels(&self) -> bool {
+        self.inner.can_remove_labels()
+    }
+
+    fn unlabel_parameter_version(
+        &self,
+        name: &str,
+        version: i64,
+        labels: &[String],
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.inner.unlabel_parameter_version(name, version, labels)
+    }
+}
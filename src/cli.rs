@@ -1,13 +1,44 @@
 pub mod cmd_copy;
 pub mod cmd_create;
+pub mod cmd_delete;
+pub mod cmd_diff;
 pub mod cmd_edit;
+pub mod cmd_exec;
+pub mod cmd_expiring;
 pub mod cmd_export;
+pub mod cmd_get;
+pub mod cmd_history;
 pub mod cmd_import;
+pub mod cmd_inspect;
+pub mod cmd_key;
+pub mod cmd_keygen;
+pub mod cmd_label;
+pub mod cmd_ls;
+pub mod cmd_move;
+pub mod cmd_rollback;
+pub mod cmd_set;
+pub mod cmd_sync;
 
+use chrono::{DateTime, Utc};
+use std::error;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+use crate::aws::parameter_diff::ParameterDiff;
+use crate::aws::parameter_store::ParameterType;
+use crate::aws::plaintext_import::InputFormat;
+use crate::backend::file_backend::FileBackend;
+use crate::backend::parameter_backend::ParameterBackend;
+use crate::backend::ssm_backend::{SsmBackend, SsmConnection};
+use crate::backend::tag_filter::{TagFilter, TagFilteredBackend};
+use crate::editor;
+use crate::environment::env_formats::EnvFormat;
+use crate::environment::env_names::EnvNaming;
+use crate::flags::list_format::ListFormat;
 use crate::flags::operation_mode::OperationMode;
+use crate::secrets::keychain_access::KeyName;
+use crate::secrets::passphrase::PassphraseSource;
+use crate::secrets::recipients::Recipient;
 
 #[derive(Clone, Debug, StructOpt)]
 /// simple & interactive manipulation of AWS SSM Parameter Store values
@@ -27,6 +58,48 @@ pub struct Keez {
     /// intermediate data structures along the way.  Be careful,
     /// sensitive information might end up in your terminal.
     debug: bool,
+    #[structopt(short = "y", long)]
+    /// Don't ask for confirmation before changing parameters.
+    ///
+    /// This is meant for scripts.  Be careful, keez will overwrite and
+    /// delete parameters without asking.  Without this flag, keez
+    /// refuses to make changes when standard input is not a terminal.
+    yes: bool,
+    #[structopt(long, parse(from_os_str))]
+    /// Use a local YAML file instead of AWS Parameter Store.
+    ///
+    /// Parameters are read from and written to the given file, which
+    /// is created if it doesn't exist yet.  This is intended for local
+    /// development, and for trying out keez without an AWS account.
+    /// Be careful, values are stored in plaintext.
+    local_store: Option<PathBuf>,
+    #[structopt(long)]
+    /// The AWS region to talk to, e.g. eu-west-1.
+    ///
+    /// If not given, the region is taken from the environment
+    /// (AWS_DEFAULT_REGION or AWS_REGION) or your AWS config file.
+    region: Option<String>,
+    #[structopt(long)]
+    /// Use credentials from this named profile in ~/.aws/credentials.
+    ///
+    /// If not given, credentials are looked up as usual: environment
+    /// variables, the default profile, or an instance role.  Note that
+    /// the profile does not select a region, use --region for that.
+    profile: Option<String>,
+    #[structopt(long)]
+    /// Send Parameter Store requests to a custom endpoint URL.
+    ///
+    /// This is useful for pointing keez at a local stand-in for AWS,
+    /// such as LocalStack, e.g. http://localhost:4566.
+    endpoint_url: Option<String>,
+    #[structopt(long = "tag", number_of_values = 1)]
+    /// Only select parameters with this tag, given as key=value.
+    ///
+    /// Pass just a key to select parameters carrying that tag with
+    /// any value.  This flag can be repeated, in which case parameters
+    /// must match all of the given tags.  It narrows down the
+    /// parameters found under a path prefix, for every command.
+    tags: Vec<TagFilter>,
     #[structopt(subcommand)]
     cmd: KeezCommand,
 }
@@ -47,6 +120,69 @@ impl Keez {
             &OperationMode::ReadWrite
         }
     }
+
+    /// Ask the user to confirm a write, unless --yes was given.
+    pub fn confirm(&self, question: &str) -> bool {
+        self.yes || editor::prompt::confirm(question)
+    }
+
+    /// Show the planned changes and ask whether to apply them.  In
+    /// dry-run mode nothing will be written, so there's nothing to
+    /// confirm.
+    pub fn confirm_plan(&self, diff: &ParameterDiff) -> bool {
+        editor::plan::print(diff);
+        *self.operation_mode() == OperationMode::ReadOnly || self.confirm("Apply these changes?")
+    }
+
+    pub fn ssm_connection(&self) -> SsmConnection {
+        SsmConnection {
+            region: self.region.clone(),
+            profile: self.profile.clone(),
+            endpoint_url: self.endpoint_url.clone(),
+        }
+    }
+
+    /// Construct the parameter backend selected by the global flags:
+    /// a local file if `--local-store` was given, AWS otherwise.
+    pub fn backend(&self) -> Result<Box<dyn ParameterBackend>, Box<dyn error::Error>> {
+        self.backend_for(&self.ssm_connection())
+    }
+
+    /// Like `backend`, for commands which neither show nor write tags.
+    /// Parameter Store takes a request per parameter to list its tags,
+    /// so they're only fetched when --tag needs them for filtering.
+    pub fn untagged_backend(&self) -> Result<Box<dyn ParameterBackend>, Box<dyn error::Error>> {
+        self.make_backend(&self.ssm_connection(), !self.tags.is_empty())
+    }
+
+    /// Like `backend`, but connect to AWS using the given connection
+    /// settings instead of the global ones.
+    pub fn backend_for(
+        &self,
+        connection: &SsmConnection,
+    ) -> Result<Box<dyn ParameterBackend>, Box<dyn error::Error>> {
+        self.make_backend(connection, true)
+    }
+
+    fn make_backend(
+        &self,
+        connection: &SsmConnection,
+        fetch_tags: bool,
+    ) -> Result<Box<dyn ParameterBackend>, Box<dyn error::Error>> {
+        let backend: Box<dyn ParameterBackend> = match &self.local_store {
+            Some(path) => Box::new(FileBackend::open(path.clone())?),
+            None => Box::new(SsmBackend::new(connection, fetch_tags, self.debug)?),
+        };
+
+        if self.tags.is_empty() {
+            Ok(backend)
+        } else {
+            Ok(Box::new(TagFilteredBackend::new(
+                backend,
+                self.tags.clone(),
+            )))
+        }
+    }
 }
 
 #[derive(Clone, Debug, StructOpt)]
@@ -74,6 +210,12 @@ pub enum KeezCommand {
     /// Note that the operation will fail if the target parameter
     /// store values already exist, that is, the operations are run
     /// with overwriting set to "disabled".
+    ///
+    /// The source and destination may live in different AWS accounts
+    /// or regions.  Parameters are copied straight from one to the
+    /// other, nothing is written to disk.  For example:{n}
+    /// keez copy --source-profile staging --destination-profile prod
+    /// --destination-region eu-west-1 /app /app
     Copy {
         /// The path prefix for selecting parameters to copy.
         source: String,
@@ -82,6 +224,18 @@ pub enum KeezCommand {
         #[structopt(short, long)]
         /// Whether to interactively edit values prior to importing.
         edit: bool,
+        #[structopt(long)]
+        /// The AWS region to read parameters from, instead of --region.
+        source_region: Option<String>,
+        #[structopt(long)]
+        /// The AWS profile to read parameters with, instead of --profile.
+        source_profile: Option<String>,
+        #[structopt(long)]
+        /// The AWS region to write parameters to, instead of --region.
+        destination_region: Option<String>,
+        #[structopt(long)]
+        /// The AWS profile to write parameters with, instead of --profile.
+        destination_profile: Option<String>,
     },
     /// Interactively create parameters in bulk
     ///
@@ -97,7 +251,84 @@ pub enum KeezCommand {
     /// This command respects your $EDITOR environment variable.  If
     /// you don't want to modify anything, simply close your editor
     /// without changing the file and the process will be aborted.
-    Create {},
+    ///
+    /// To seed the editor with existing values instead of the example,
+    /// pass --from-file with a .env file, a JSON object (nested
+    /// objects become deeper paths), or the JSON printed by `aws ssm
+    /// get-parameters-by-path --with-decryption`.  The format is
+    /// guessed, or can be given with --input-format.  Names from .env
+    /// and JSON files are placed under --prefix, which they need, and
+    /// get the type given with --type, SecureString by default.  For
+    /// example:{n}
+    /// keez create --from-file .env --prefix /app/dev
+    Create {
+        #[structopt(long, parse(from_os_str))]
+        /// Read the initial parameters from this plaintext file.
+        from_file: Option<PathBuf>,
+        #[structopt(long)]
+        /// The format of --from-file: dotenv, json or aws-cli.
+        input_format: Option<InputFormat>,
+        #[structopt(long)]
+        /// The path prefix to put parameters from --from-file under.
+        /// Required for .env and JSON files.
+        prefix: Option<String>,
+        #[structopt(long = "type", default_value = "SecureString")]
+        /// The type for parameters from .env and JSON files.
+        parameter_type: ParameterType,
+    },
+    /// Delete all parameters under a given prefix
+    ///
+    /// This command recursively queries all parameters with path
+    /// prefix <prefix> from the AWS Systems Manager Parameter Store,
+    /// shows you the list, and deletes them once you confirm.
+    ///
+    /// For example:{n}
+    /// keez delete /old-service
+    ///
+    /// Pass --edit to get an editor session listing all the keys
+    /// first; any lines you remove there are kept in Parameter Store.
+    /// Use --dry-run to see what would be deleted without touching
+    /// anything.
+    Delete {
+        /// The path prefix for selecting parameters to delete.
+        prefix: String,
+        #[structopt(short, long)]
+        /// Whether to interactively narrow down the keys to delete.
+        edit: bool,
+    },
+    /// Show the differences between two sets of parameters
+    ///
+    /// Each side is either a path prefix in Parameter Store, or, with
+    /// --from-file or --to-file, a file written by `keez export`.
+    /// Keys are compared relative to their prefix, so /preprod/db/host
+    /// is compared with /prod/db/host.  The output lists keys which
+    /// were added (+), removed (-) or changed (~) when going from
+    /// <from> to <to>.
+    ///
+    /// For example:{n}
+    /// keez diff /prod /preprod{n}
+    /// keez diff --from-file ./prod.yaml.enc /prod
+    ///
+    /// SecureString values are masked unless you pass --reveal.  The
+    /// exit status is 1 if there are differences, like diff(1).
+    Diff {
+        /// The path prefix (or export file) to compare from.
+        from: String,
+        /// The path prefix (or export file) to compare to.
+        to: String,
+        #[structopt(long)]
+        /// Treat <from> as a file written by `keez export`.
+        from_file: bool,
+        #[structopt(long)]
+        /// Treat <to> as a file written by `keez export`.
+        to_file: bool,
+        #[structopt(long)]
+        /// Show SecureString values instead of masking them.
+        reveal: bool,
+        #[structopt(long)]
+        /// Decrypt export files with a passphrase: prompt, env:NAME or fd:N.
+        passphrase: Option<PassphraseSource>,
+    },
     /// Interactively edit existing parameters under a given prefix
     ///
     /// This command recursively queries all parameters from the AWS
@@ -107,6 +338,21 @@ pub enum KeezCommand {
     /// This allows for easy modification of the parameter values.
     /// Beats using the AWS Console amirite?!
     ///
+    /// Policies (Expiration, ExpirationNotification and
+    /// NoChangeNotification) can be given for Advanced or
+    /// Intelligent-Tiering tier parameters, for instance:{n}
+    /// policies:{n}
+    ///   - type: Expiration{n}
+    ///     timestamp: 2030-01-01T00:00:00Z{n}
+    ///   - type: ExpirationNotification{n}
+    ///     before: 14{n}
+    ///     unit: Days
+    ///
+    /// Keys you add to the YAML are created, and keys you remove from
+    /// it are deleted.  Before anything is written, keez shows which
+    /// keys will be created, updated and deleted, and asks you to
+    /// confirm.
+    ///
     /// For example:{n}
     /// keez edit /path/prefix/foo
     ///
@@ -117,6 +363,53 @@ pub enum KeezCommand {
         /// The path prefix for selecting parameters to edit.
         prefix: String,
     },
+    /// Run a program with parameters as environment variables
+    ///
+    /// This command reads all parameters under each --prefix, turns
+    /// them into environment variables, and runs <command> with those
+    /// added to its environment.  Values are passed straight to the
+    /// program, nothing is written to disk.
+    ///
+    /// For example:{n}
+    /// keez exec --prefix /app/common --prefix /app/dev -- ./server --port 8080
+    ///
+    /// By default, a parameter's variable name is the last segment of
+    /// its path, uppercased, with anything but letters, digits and
+    /// underscores replaced by underscores: /app/dev/db-host becomes
+    /// DB_HOST.  Pass --env-names relative-path to use the whole path
+    /// below the prefix instead, so /app/dev/db/host becomes DB_HOST.
+    ///
+    /// If a variable comes from several prefixes, the prefix given
+    /// last wins.  Two parameters under the same prefix mapping to the
+    /// same variable is an error.  Parameters take precedence over
+    /// variables already set in your environment.
+    Exec {
+        #[structopt(short, long = "prefix", number_of_values = 1, required = true)]
+        /// A path prefix to read parameters from.  Can be repeated.
+        prefixes: Vec<String>,
+        #[structopt(long, default_value = "last-segment")]
+        /// How to name variables: last-segment or relative-path.
+        env_names: EnvNaming,
+        #[structopt(required = true)]
+        /// The program to run, followed by its arguments.
+        command: Vec<String>,
+    },
+    /// List parameters under a prefix which are about to expire
+    ///
+    /// Parameters in the Advanced tier can carry an Expiration policy,
+    /// after which Parameter Store deletes them.  This command lists
+    /// the parameters under <prefix> whose expiration falls within the
+    /// next --days days (or has already passed), soonest first.
+    ///
+    /// For example:{n}
+    /// keez expiring --days 7 /certificates
+    Expiring {
+        /// The path prefix for selecting parameters to check.
+        prefix: String,
+        #[structopt(long, default_value = "30")]
+        /// How many days ahead to look.
+        days: i64,
+    },
     /// Export is useful for migrating a group of parameters to another AWS account or region.
     ///
     /// This command recursively queries all parameters with prefix
@@ -137,20 +430,131 @@ pub enum KeezCommand {
     /// parameters somewhere new, possibly to another AWS account or
     /// region.
     ///
+    /// Pass --label to export the versions carrying that label (see
+    /// `keez label`) instead of the latest values.  Parameters without
+    /// such a version are left out.
+    ///
+    /// Alternatively, pass --format instead of --export-filename to
+    /// print the parameters to stdout, unencrypted, as environment
+    /// variables for other tools to read.  The formats are dotenv,
+    /// bash (or zsh), fish, json and docker (for `docker run
+    /// --env-file`).  Values are quoted as each format requires.
+    /// Variables are named as for `keez exec`, see --env-names.  For
+    /// example:{n}
+    /// keez export --format dotenv /app/dev > .env{n}
+    /// eval "$(keez export --format bash /app/dev)"
+    ///
     /// Note that if you want to copy parameters within one AWS region
     /// and account, you can simply use `keez copy`.
+    ///
+    /// The keychain key only exists on your machine.  To hand an
+    /// export to a teammate or a CI job, pass --passphrase to encrypt
+    /// with a key derived from a passphrase instead.  The passphrase is
+    /// asked for (prompt), read from an environment variable
+    /// (env:NAME), or read from an open file descriptor (fd:N).  The
+    /// key derivation parameters are stored in the file, so the same
+    /// passphrase is all that's needed to import it anywhere.  For
+    /// example:{n}
+    /// keez export --passphrase prompt --export-filename ./foo.yaml.enc /foo
+    ///
+    /// To hand parameters to specific people without sharing a secret,
+    /// encrypt to their public keys with --recipient, which can be
+    /// repeated.  Each of them creates a key pair with `keez keygen`
+    /// first, and sends you the public key it prints.  Include your own
+    /// public key if you want to be able to read the file yourself.
+    /// `keez import` decrypts such files with your identity
+    /// automatically.  For example:{n}
+    /// keez export --recipient keez-x25519:... --export-filename ./foo.yaml.enc /foo
+    ///
+    /// Pass --key-name to encrypt with a named keychain key instead of
+    /// the default one, for instance one per project or environment, so
+    /// that sharing one key doesn't give access to every export.  The
+    /// name is recorded in the file, and `keez import` picks the same
+    /// key automatically.  See `keez key` to create and share keys.
+    ///
+    /// The file starts with an unencrypted header recording how it was
+    /// encrypted, when, and where the parameters came from, but no
+    /// values.  Use `keez inspect` to read it.  The source account is
+    /// looked up with sts:GetCallerIdentity, and left out of the header
+    /// if you aren't allowed to call it.
     Export {
         /// The path prefix for selecting parameters to export.
         source: String,
         #[structopt(long, parse(from_os_str))]
         /// File to export parameters to, prior to importing to another account.
-        export_filename: PathBuf,
+        export_filename: Option<PathBuf>,
         #[structopt(short = "I", long)]
         /// For debugging: print results of export to stdout.
         ///
         /// Use this option with care, because you risk exposing
         /// secrets.
         insecure_output: bool,
+        #[structopt(long)]
+        /// Export the versions carrying this label instead of the latest.
+        label: Option<String>,
+        #[structopt(long)]
+        /// Print to stdout as dotenv, bash, zsh, fish, json or docker.
+        format: Option<EnvFormat>,
+        #[structopt(long, default_value = "last-segment")]
+        /// With --format, how to name variables: last-segment or relative-path.
+        env_names: EnvNaming,
+        #[structopt(long)]
+        /// Encrypt with a passphrase instead: prompt, env:NAME or fd:N.
+        passphrase: Option<PassphraseSource>,
+        #[structopt(long = "recipient", number_of_values = 1)]
+        /// Encrypt to this public key instead, see `keez keygen`.  Can be repeated.
+        recipients: Vec<Recipient>,
+        #[structopt(long)]
+        /// Encrypt with this named keychain key instead of the default one.
+        key_name: Option<KeyName>,
+    },
+    /// Print the value of a single parameter
+    ///
+    /// This command prints the value of <name> to stdout, and nothing
+    /// else, which makes it easy to use from shell scripts.  The latest
+    /// version is printed, unless you select an earlier one with
+    /// --version or by label with --label.
+    ///
+    /// For example:{n}
+    /// DB_PASSWORD=$(keez get /app/db/password)
+    ///
+    /// With --json, the value is printed as part of a JSON object,
+    /// along with the parameter's type, attributes, version, labels
+    /// and when and by whom it was last modified.
+    Get {
+        /// The full name of the parameter to print.
+        name: String,
+        #[structopt(long)]
+        /// The version to print, instead of the latest one.
+        version: Option<i64>,
+        #[structopt(long)]
+        /// Print the version carrying this label, instead of the latest one.
+        label: Option<String>,
+        #[structopt(long)]
+        /// Print the value and metadata as JSON.
+        json: bool,
+    },
+    /// Show the stored versions of a parameter
+    ///
+    /// This command lists every version Parameter Store kept of <name>,
+    /// oldest first, with when and by whom it was written, and what
+    /// changed compared to the version before it.  Pass --recursive to
+    /// show the history of every parameter under the prefix <name>.
+    ///
+    /// For example:{n}
+    /// keez history /app/db/password{n}
+    /// keez history --recursive /app
+    ///
+    /// SecureString values are masked unless you pass --reveal.
+    History {
+        /// The parameter (or, with --recursive, path prefix) to show.
+        name: String,
+        #[structopt(short, long)]
+        /// Treat <name> as a path prefix and show every parameter under it.
+        recursive: bool,
+        #[structopt(long)]
+        /// Show SecureString values instead of masking them.
+        reveal: bool,
     },
     /// Import parameters from a previous `keez export`.
     ///
@@ -186,6 +590,19 @@ pub enum KeezCommand {
     ///
     /// Notice how the original prefix, /foo, has been replaced by the
     /// new prefix you specified, /baz/quux.
+    ///
+    /// If the export was made with --passphrase, pass --passphrase
+    /// here too, for instance:{n}
+    /// KEEZ_PASSPHRASE=... keez import --passphrase env:KEEZ_PASSPHRASE
+    /// --import-filename ./foo.yaml.enc /foo
+    ///
+    /// Pass --input-format to import a plaintext file instead: dotenv
+    /// for a .env file, json for a JSON object (nested objects become
+    /// deeper paths), or aws-cli for the output of `aws ssm
+    /// get-parameters-by-path --with-decryption`.  Parameters from .env
+    /// and JSON files get the type given with --type, SecureString by
+    /// default.  For example:{n}
+    /// keez import --input-format dotenv --import-filename .env /app/dev
     Import {
         /// The target path for importing parameters.
         destination: String,
@@ -195,5 +612,290 @@ pub enum KeezCommand {
         #[structopt(short, long)]
         /// Whether to interactively edit values prior to importing.
         edit: bool,
+        #[structopt(long)]
+        /// Read a plaintext file in this format: dotenv, json or aws-cli.
+        input_format: Option<InputFormat>,
+        #[structopt(long = "type", default_value = "SecureString")]
+        /// The type for parameters from .env and JSON files.
+        parameter_type: ParameterType,
+        #[structopt(long)]
+        /// Decrypt with a passphrase: prompt, env:NAME or fd:N.
+        passphrase: Option<PassphraseSource>,
+        #[structopt(long)]
+        /// Decrypt with this keychain key, rather than the one named in the file.
+        key_name: Option<KeyName>,
+    },
+    /// Describe an export file without decrypting it
+    ///
+    /// This command prints the header of a file written by `keez
+    /// export`: the format version, how it was encrypted (and with
+    /// which key, or to whom), when it was made, which prefix, AWS
+    /// account and region the parameters came from, and how many there
+    /// are.  No keys are needed, and no values are shown.
+    ///
+    /// For example:{n}
+    /// keez inspect ./foo.yaml.enc
+    Inspect {
+        #[structopt(parse(from_os_str))]
+        /// The export file to describe.
+        file: PathBuf,
+    },
+    /// Manage the keychain key which exports are encrypted with
+    ///
+    /// Unless you pass --passphrase or --recipient, `keez export`
+    /// encrypts with a random key kept in your system keychain, which
+    /// is created the first time it's needed.  These commands show its
+    /// fingerprint, back it up or share it, and replace it.
+    ///
+    /// For example:{n}
+    /// keez key export > keez-key.txt{n}
+    /// keez key import < keez-key.txt{n}
+    /// keez key rotate{n}
+    /// keez key reencrypt ./foo.yaml.enc ./bar.yaml.enc
+    ///
+    /// Pass --key-name to manage a named key instead of the default
+    /// one.  Each name has its own keychain entry, so e.g. a key shared
+    /// with one team doesn't decrypt another team's exports:{n}
+    /// keez key --key-name payments rotate
+    Key {
+        #[structopt(long, default_value = "default")]
+        /// The keychain key to manage.
+        key_name: KeyName,
+        #[structopt(subcommand)]
+        command: KeyCommand,
+    },
+    /// Create your identity for receiving encrypted exports
+    ///
+    /// This command generates an X25519 key pair, stores it in your
+    /// system keychain, and prints the public key.  Others can pass
+    /// that public key to `keez export --recipient`, to encrypt an
+    /// export which only you (and any other recipients) can decrypt.
+    /// `keez import` uses your identity automatically.
+    ///
+    /// If you already have an identity, its public key is printed.
+    /// Pass --force to replace it with a new one; exports encrypted to
+    /// the old one can then no longer be decrypted.
+    Keygen {
+        #[structopt(long)]
+        /// Replace an existing identity.
+        force: bool,
+    },
+    /// Attach labels to parameter versions, or remove them
+    ///
+    /// Parameter Store lets you label versions of a parameter, which
+    /// is handy for pinning the values a release should use.  This
+    /// command attaches <labels> to the latest version of <name>, or
+    /// to the version given with --version.  Pass --recursive to label
+    /// every parameter under the prefix <name>.  A label can only be on
+    /// one version of a parameter at a time, so it moves away from
+    /// whichever version carried it before.
+    ///
+    /// For example:{n}
+    /// keez label --recursive /app release-42
+    ///
+    /// With --remove, the labels are taken off again, from whichever
+    /// version carries them.  This only works with --local-store for
+    /// now; on AWS, use the console or the AWS CLI to remove labels.
+    /// Labels must not begin with a number, "aws" or "ssm".  Use `keez
+    /// get --label` or `keez export --label` to read the labelled
+    /// values back.
+    Label {
+        /// The parameter (or, with --recursive, path prefix) to label.
+        name: String,
+        #[structopt(required = true)]
+        /// The labels to attach or remove.
+        labels: Vec<String>,
+        #[structopt(short, long)]
+        /// Treat <name> as a path prefix and label every parameter under it.
+        recursive: bool,
+        #[structopt(long)]
+        /// The version to label, instead of the latest one.
+        version: Option<i64>,
+        #[structopt(long)]
+        /// Remove the labels instead of attaching them (--local-store only).
+        remove: bool,
+    },
+    /// List the parameters under a prefix, without their values
+    ///
+    /// This command shows which parameters exist under <prefix>, as a
+    /// tree, with each parameter's type, tier, version and when it was
+    /// last modified.  Values are never printed, so it's safe to use
+    /// for browsing.
+    ///
+    /// For example:{n}
+    /// keez ls /app{n}
+    /// keez ls --depth 2 --output flat /app
+    ///
+    /// Pass --non-recursive to only list the parameters directly under
+    /// <prefix>, or --depth to stop descending after that many levels;
+    /// deeper directories are then summarised.  Use --output flat for
+    /// a list of full names, or --output json for scripts.
+    Ls {
+        /// The path prefix to list.
+        prefix: String,
+        #[structopt(long)]
+        /// Only list parameters directly under <prefix>.
+        non_recursive: bool,
+        #[structopt(long)]
+        /// How many levels below <prefix> to show.
+        depth: Option<usize>,
+        #[structopt(short, long, default_value = "tree")]
+        /// How to print the parameters: tree, flat or json.
+        output: ListFormat,
+    },
+    /// Rename a whole tree of parameters to another prefix
+    ///
+    /// This command works like `keez copy`, except that the original
+    /// parameters are deleted afterwards.  The originals are only
+    /// deleted once every parameter has been written to its new
+    /// location.  If writing fails halfway, for instance because one
+    /// of the target keys already exists, the parameters created so
+    /// far are removed again and the originals are left alone.
+    ///
+    /// For example:{n}
+    /// keez move /svc/old-name /svc/new-name
+    Move {
+        /// The path prefix for selecting parameters to move.
+        source: String,
+        /// The path where you would like to move parameters to.
+        destination: String,
+    },
+    /// Restore parameters to an earlier version
+    ///
+    /// This command looks up the history of <name> and writes the
+    /// chosen earlier version back as a new version.  Pick the version
+    /// either by number, with --to-version, or by time, with --to-time,
+    /// in which case the version which was current at that moment is
+    /// restored.  Pass --recursive to roll back every parameter under
+    /// the prefix <name>; this works best with --to-time.
+    ///
+    /// For example:{n}
+    /// keez rollback --to-version 3 /app/db/password{n}
+    /// keez rollback --recursive --to-time 2020-10-01T12:00:00Z /app
+    ///
+    /// The planned changes are shown, and you're asked to confirm
+    /// before anything is written.  Tags are not versioned by
+    /// Parameter Store, so they are left as they are.  Parameters which
+    /// didn't exist yet at the given time are left alone.
+    Rollback {
+        /// The parameter (or, with --recursive, path prefix) to restore.
+        name: String,
+        #[structopt(short, long)]
+        /// Treat <name> as a path prefix and restore every parameter under it.
+        recursive: bool,
+        #[structopt(long)]
+        /// The version number to restore.
+        to_version: Option<i64>,
+        #[structopt(long)]
+        /// Restore the version current at this time, e.g. 2020-10-01T12:00:00Z.
+        to_time: Option<DateTime<Utc>>,
+    },
+    /// Write the value of a single parameter
+    ///
+    /// This command stores <value> as the parameter <name>, which
+    /// makes it easy to use from shell scripts.  If <value> isn't
+    /// given, it's read from the file given with --from-file, or else
+    /// from stdin.  A single trailing newline is stripped from values
+    /// read from a file or stdin.
+    ///
+    /// For example:{n}
+    /// keez set /app/db/host db.example.com{n}
+    /// pwgen 32 1 | keez set /app/db/password
+    ///
+    /// New parameters are created as SecureString, unless you pass
+    /// --type.  Existing parameters are only replaced if you pass
+    /// --overwrite, in which case their type, description, tags and
+    /// other attributes are kept, apart from the type if you pass
+    /// --type.
+    Set {
+        /// The full name of the parameter to write.
+        name: String,
+        /// The value to store.  Read from stdin if not given.
+        value: Option<String>,
+        #[structopt(long, parse(from_os_str))]
+        /// Read the value from this file.
+        from_file: Option<PathBuf>,
+        #[structopt(long = "type")]
+        /// The parameter type: String, SecureString or StringList.
+        parameter_type: Option<ParameterType>,
+        #[structopt(long)]
+        /// Replace the parameter if it already exists.
+        overwrite: bool,
+    },
+    /// Make the parameters under one prefix mirror another prefix
+    ///
+    /// This command compares the parameters under <source> with those
+    /// under <destination>, relative to their prefixes.  It then
+    /// creates keys which are missing from <destination> and
+    /// overwrites keys whose value or type differs.  The planned
+    /// changes are shown, and you're asked to confirm before anything
+    /// is written.
+    ///
+    /// For example:{n}
+    /// keez sync /preprod /prod
+    ///
+    /// Keys which only exist under <destination> are left alone,
+    /// unless you pass --delete.
+    Sync {
+        /// The path prefix to take parameters from.
+        source: String,
+        /// The path prefix which should end up mirroring <source>.
+        destination: String,
+        #[structopt(long)]
+        /// Also delete keys which don't exist under <source>.
+        delete: bool,
+    },
+}
+
+#[derive(Clone, Debug, StructOpt)]
+pub enum KeyCommand {
+    /// Print the fingerprint of your keychain key
+    ///
+    /// Export files record the fingerprint of the key they were
+    /// encrypted with, which `keez inspect` shows.  Compare the two to
+    /// find out whether you can import a file.
+    Fingerprint,
+    /// Print your keychain key, encoded for safekeeping
+    ///
+    /// The encoded key includes its fingerprint, so that a damaged
+    /// copy is noticed when it's imported.  Anyone holding it can
+    /// decrypt your keychain-encrypted exports, so store it somewhere
+    /// safe, such as a password manager.
+    Export,
+    /// Replace your keychain key with one from `keez key export`
+    ///
+    /// Use this to restore a backed up key, or to share a teammate's
+    /// key so you can import each other's exports.  The encoded key is
+    /// read from stdin if not given, which keeps it out of your shell
+    /// history.  Your current key is printed in encoded form, so you
+    /// can back it up, and kept in your keychain as a retired key, so
+    /// existing exports can be moved over with `keez key reencrypt`.
+    Import {
+        /// The encoded key, as printed by `keez key export`.
+        encoded_key: Option<String>,
+    },
+    /// Replace your keychain key with a newly generated one
+    ///
+    /// Your current key is printed in encoded form, so you can back it
+    /// up, and kept in your keychain as a retired key.  Exports made
+    /// with it can no longer be imported until you move them over to
+    /// the new key with `keez key reencrypt`.
+    Rotate,
+    /// Re-encrypt export files from a retired key to the current one
+    ///
+    /// Each file is decrypted with the retired key it was made with
+    /// (every key replaced by `keez key rotate` or `keez key import` is
+    /// kept), and written back encrypted with your current key.  Pass
+    /// --old-key to decrypt with a different key instead.  Files which
+    /// already use the current key are left alone.  Each file is
+    /// replaced only once its new version has been written in full.
+    Reencrypt {
+        #[structopt(long)]
+        /// The key the files were encrypted with, as printed by `keez
+        /// key export`.
+        old_key: Option<String>,
+        #[structopt(parse(from_os_str), required = true)]
+        /// The export files to re-encrypt, in place.
+        files: Vec<PathBuf>,
     },
 }
@@ -1,24 +1,40 @@
+use std::process;
+
 use crate::aws;
+use crate::backend::ssm_backend::SsmConnection;
 use crate::cli;
 use crate::editor;
 use crate::flags;
 
+use aws::parameter_diff::ParameterDiff;
+use aws::parameter_store::ParameterCollection;
 use flags::operation_mode::OperationMode;
 
 // The `copy` command takes a source and target prefix (e.g., /foo and
 // /bar) and copies all values under /foo to values at the same
 // hierarchy under /bar.  It replaces the initial `/foo' component
 // with /bar.  This also works if the target is deep, such as
-// /bar/baz/quux.
+// /bar/baz/quux.  The source and destination each get their own
+// connection, so they may live in different accounts or regions.
 pub fn run(
     args: cli::Keez,
     source: String,
     destination: String,
     edit: bool,
+    source_connection: SsmConnection,
+    destination_connection: SsmConnection,
     operation_mode: OperationMode,
 ) {
+    let source_backend = args.backend_for(&source_connection).unwrap();
+    let destination_backend = args.backend_for(&destination_connection).unwrap();
+
+    if args.debug {
+        eprintln!("Source connection: {:?}", source_connection);
+        eprintln!("Destination connection: {:?}", destination_connection);
+    }
+
     let parameters =
-        aws::parameter_store::get_parameters_by_path(source.clone(), args.debug).unwrap();
+        aws::parameter_store::get_parameters_by_path(&*source_backend, source.clone()).unwrap();
 
     let mut rerooted_parameters =
         aws::parameter_store::reroot_parameters(parameters.clone(), destination.clone()).unwrap();
@@ -29,5 +45,23 @@ pub fn run(
                 .unwrap();
     }
 
-    aws::parameter_store::push_new_parameters(rerooted_parameters, operation_mode).unwrap();
+    let plan = ParameterDiff::between_absolute(
+        &ParameterCollection::new(destination),
+        &rerooted_parameters,
+    );
+    if !args.confirm_plan(&plan) {
+        eprintln!("Aborted, nothing was copied.");
+        return;
+    }
+
+    // Copying never overwrites, so stop at a key which already exists
+    // and leave updating it to `sync`.
+    if let Err(e) = aws::parameter_store::push_new_parameters(
+        &*destination_backend,
+        rerooted_parameters,
+        operation_mode,
+    ) {
+        eprintln!("Error: {}  Use `keez sync` to update existing keys.", e);
+        process::exit(1);
+    }
 }
@@ -1,11 +1,50 @@
+use std::path::PathBuf;
+use std::process;
+
 use crate::aws;
 use crate::cli;
 use crate::editor;
 use crate::flags;
 
+use aws::parameter_diff::ParameterDiff;
+use aws::parameter_store::ParameterType;
+use aws::plaintext_import::InputFormat;
 use flags::operation_mode::OperationMode;
 
-pub fn run(args: cli::Keez, operation_mode: OperationMode) {
+pub fn run(
+    args: cli::Keez,
+    from_file: Option<PathBuf>,
+    input_format: Option<InputFormat>,
+    prefix: Option<String>,
+    default_type: ParameterType,
+    operation_mode: OperationMode,
+) {
+    match from_file {
+        Some(path) => {
+            let input_format = aws::plaintext_import::detect_format(&path, input_format).unwrap();
+            if prefix.is_none() && !input_format.has_full_names() {
+                eprintln!(
+                    "Please pass --prefix to say where the parameters from {} should go.",
+                    path.display()
+                );
+                process::exit(2);
+            }
+
+            let mut parsed =
+                aws::plaintext_import::read(&path, Some(input_format), default_type).unwrap();
+            if let Some(prefix) = prefix {
+                parsed = aws::parameter_store::reroot_parameters(parsed, prefix).unwrap();
+            }
+
+            let new_parameter_blob =
+                editor::edit_loop::interactive_edit_parameters(parsed, args.debug).unwrap();
+            create(args, new_parameter_blob, operation_mode);
+        }
+        None => create_from_example(args, operation_mode),
+    }
+}
+
+fn create_from_example(args: cli::Keez, operation_mode: OperationMode) {
     // Create an example blob of YAML for the user to ape:
     let example = String::from(
         "---
@@ -16,6 +55,9 @@ parameters:
   /this/is/another:
     value: bar
     type: SecureString
+    description: <optional description>
+    tags:
+      team: optional-too
   /different:
     value: baz
     type: SecureString
@@ -32,15 +74,34 @@ parameters:
         return;
     }
 
+    create(args, new_parameter_blob, operation_mode);
+}
+
+fn create(
+    args: cli::Keez,
+    new_parameter_blob: aws::parameter_store::ParameterCollection,
+    operation_mode: OperationMode,
+) {
     if args.debug {
         eprintln!("New parameter blob:");
         eprintln!("{:?}", new_parameter_blob);
     }
 
-    eprintln!("Create blob contains the following keys:");
-    for (key, _param) in new_parameter_blob.parameters() {
-        eprintln!("  - {}", key);
+    let plan = ParameterDiff::between_absolute(
+        &aws::parameter_store::ParameterCollection::new(String::new()),
+        &new_parameter_blob,
+    );
+    aws::parameter_store::validate_changes(&plan).unwrap();
+    if !args.confirm_plan(&plan) {
+        eprintln!("Aborted, nothing was created.");
+        return;
     }
 
-    aws::parameter_store::push_new_parameters(new_parameter_blob, operation_mode).unwrap();
+    let backend = args.backend().unwrap();
+    if let Err(e) =
+        aws::parameter_store::push_new_parameters(&*backend, new_parameter_blob, operation_mode)
+    {
+        eprintln!("Error: {}  Use `keez edit` to update existing keys.", e);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,47 @@
+use crate::aws;
+use crate::cli;
+use crate::editor;
+use crate::flags;
+
+use flags::operation_mode::OperationMode;
+
+// The `delete` command removes every parameter under a prefix, after
+// optionally letting the user narrow down the selection in their
+// editor, and asking for confirmation first.
+pub fn run(args: cli::Keez, prefix: String, edit: bool, operation_mode: OperationMode) {
+    let backend = args.backend().unwrap();
+
+    let parameters =
+        aws::parameter_store::get_parameters_by_path(&*backend, prefix.clone()).unwrap();
+
+    let mut keys: Vec<String> = parameters.parameters().keys().cloned().collect();
+    keys.sort();
+
+    if keys.is_empty() {
+        eprintln!("No parameters found under {}, nothing to do.", prefix);
+        return;
+    }
+
+    if edit {
+        keys = editor::edit_loop::interactive_select_keys(keys, "deleted").unwrap();
+
+        if keys.is_empty() {
+            eprintln!("You didn't leave any keys to delete, so we'll quit now.");
+            return;
+        }
+    }
+
+    eprintln!("The following {} keys will be deleted:", keys.len());
+    for key in &keys {
+        eprintln!("  - {}", key);
+    }
+
+    if operation_mode == OperationMode::ReadWrite
+        && !args.confirm("Really delete these parameters?")
+    {
+        eprintln!("Aborted, nothing was deleted.");
+        return;
+    }
+
+    aws::parameter_store::delete_parameters(&*backend, keys, operation_mode).unwrap();
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+use std::process;
+
+use crate::aws;
+use crate::backend::parameter_backend::ParameterBackend;
+use crate::cli;
+use crate::secrets;
+
+use aws::parameter_diff::ParameterDiff;
+use aws::parameter_store::ParameterCollection;
+use secrets::passphrase::PassphraseSource;
+
+// The `diff` command compares two sets of parameters, each of which
+// is either a live prefix or a `keez export` file.  Like diff(1), it
+// exits with status 1 if there are differences.
+pub fn run(
+    args: cli::Keez,
+    from: String,
+    from_file: bool,
+    to: String,
+    to_file: bool,
+    reveal: bool,
+    passphrase: Option<PassphraseSource>,
+) {
+    let backend = args.backend().unwrap();
+
+    let passphrase = passphrase.map(|source| secrets::passphrase::read(&source, false).unwrap());
+
+    let old = load(
+        &*backend,
+        from,
+        from_file,
+        passphrase.as_deref(),
+        args.debug,
+    );
+    let new = load(&*backend, to, to_file, passphrase.as_deref(), args.debug);
+
+    let diff = ParameterDiff::between(&old, &new);
+
+    if diff.is_empty() {
+        eprintln!("No differences found.");
+        return;
+    }
+
+    for line in diff.render(reveal) {
+        println!("{}", line);
+    }
+    process::exit(1);
+}
+
+fn load(
+    backend: &dyn ParameterBackend,
+    location: String,
+    is_file: bool,
+    passphrase: Option<&str>,
+    debug: bool,
+) -> ParameterCollection {
+    let parameters = if is_file {
+        secrets::export_file::read(Path::new(&location), passphrase, None, debug).unwrap()
+    } else {
+        aws::parameter_store::get_parameters_by_path(backend, location.clone()).unwrap()
+    };
+
+    eprintln!(
+        "Loaded {} parameters from {} (prefix {}).",
+        parameters.parameters().len(),
+        location,
+        parameters.prefix()
+    );
+
+    parameters
+}
@@ -1,39 +1,156 @@
 use crate::aws;
+use crate::backend::parameter_backend::ParameterBackend;
 use crate::cli;
 use crate::editor;
 use crate::flags;
 
+use aws::parameter_diff::{Change, ParameterDiff};
+use aws::parameter_store::{Parameter, ParameterCollection};
 use flags::operation_mode::OperationMode;
 
 pub fn run(args: cli::Keez, prefix: String, operation_mode: OperationMode) {
-    let ps = aws::parameter_store::get_parameters_by_path(prefix, args.debug);
+    let backend = args.backend().unwrap();
+    let ps = aws::parameter_store::get_parameters_by_path(&*backend, prefix.clone());
 
     if args.debug {
         eprintln!("Raw output from Parameter Store:");
         eprintln!("{:?}", ps);
     }
 
-    let original_parameters = ps.unwrap();
+    let mut base = ps.unwrap();
 
     eprintln!(
         "Returned {} parameters from store.",
-        original_parameters.parameters().len()
+        base.parameters().len()
     );
 
-    let after_edit =
-        editor::edit_loop::interactive_edit_parameters(original_parameters.clone(), args.debug)
-            .unwrap();
+    let mut after_edit =
+        editor::edit_loop::interactive_edit_parameters(base.clone(), args.debug).unwrap();
 
-    if after_edit == original_parameters {
-        eprintln!("You don't appear to have modified anything, so we'll quit now.");
-        return;
-    }
+    // Keep going until the edits apply cleanly on top of what's
+    // currently in the store.  Whenever somebody else changed one of
+    // the keys we're about to touch, merge their changes with ours and
+    // let the user sort out any conflicts in the editor.
+    let diff = loop {
+        // Keys may have been renamed, added or removed in the editor, so
+        // compare full key names rather than paths relative to the prefix.
+        let diff = ParameterDiff::between_absolute(&base, &after_edit);
+
+        if diff.is_empty() {
+            eprintln!("You don't appear to have modified anything, so we'll quit now.");
+            return;
+        }
+
+        for (key, change) in diff.changes() {
+            if let Change::Added(_) = change {
+                aws::parameter_store::check_path(key.clone()).unwrap();
+            }
+        }
+        aws::parameter_store::validate_changes(&diff).unwrap();
+
+        let mut changed_keys =
+            aws::parameter_store::find_conflicts(&*backend, &base, &diff).unwrap();
+        if changed_keys.is_empty() {
+            if !args.confirm_plan(&diff) {
+                eprintln!("Aborted, nothing was changed.");
+                return;
+            }
+
+            // Check again right before writing, as the keys may have
+            // changed while the plan was waiting for confirmation.
+            changed_keys = aws::parameter_store::find_conflicts(&*backend, &base, &diff).unwrap();
+            if changed_keys.is_empty() {
+                break diff;
+            }
+        }
+
+        let theirs =
+            aws::parameter_store::get_parameters_by_path(&*backend, prefix.clone()).unwrap();
+        let (merged, conflicts) = aws::parameter_diff::three_way_merge(&base, &theirs, &after_edit);
+
+        eprintln!("The following keys were changed by someone else while you were editing:");
+        for key in &changed_keys {
+            for line in describe_conflict(&*backend, key, &base, &theirs, &after_edit, false) {
+                eprintln!("  {}", line);
+            }
+        }
+
+        if !args.confirm("Re-open the editor to merge your changes with theirs?") {
+            eprintln!("Aborted, nothing was changed.");
+            return;
+        }
+
+        let mut header = String::from(
+            "Some keys were changed by someone else while you were editing.\n\
+             Changes which didn't overlap with yours have been merged in below.\n",
+        );
+        for key in &conflicts {
+            header.push('\n');
+            for line in describe_conflict(&*backend, key, &base, &theirs, &after_edit, true) {
+                header.push_str(&line);
+                header.push('\n');
+            }
+        }
+        if !conflicts.is_empty() {
+            header.push_str(
+                "\nThe YAML below contains your version of these keys; adjust as needed.\n",
+            );
+        }
+
+        after_edit =
+            editor::edit_loop::interactive_edit_parameters_with_header(merged, &header, args.debug)
+                .unwrap();
+        base = theirs;
+    };
+
+    aws::parameter_store::apply_diff(&*backend, &diff, "", operation_mode).unwrap();
+}
+
+// Describe the three versions of a conflicting key: what we started
+// from, what's in the store now, and what the user wants.
+fn describe_conflict(
+    backend: &dyn ParameterBackend,
+    key: &str,
+    base: &ParameterCollection,
+    theirs: &ParameterCollection,
+    mine: &ParameterCollection,
+    reveal: bool,
+) -> Vec<String> {
+    let describe = |param: Option<&Parameter>| match param {
+        Some(param) => format!(
+            "{} ({}){}",
+            param.display_value(reveal),
+            param.parameter_type(),
+            param
+                .version()
+                .map_or(String::new(), |version| format!(", version {}", version))
+        ),
+        None => "(doesn't exist)".to_string(),
+    };
 
-    eprintln!("Edited blob contains the following keys:");
-    for (key, _param) in after_edit.parameters() {
-        eprintln!("  - {}", key);
+    let mut their_description = match theirs.parameters().get(key) {
+        None if base.parameters().contains_key(key) => "deleted remotely".to_string(),
+        their_param => describe(their_param),
+    };
+    // The history only adds detail, so don't give up on the merge if it
+    // can't be fetched.
+    let history = backend.parameter_history(key).unwrap_or_default();
+    if let Some(latest) = history.last() {
+        if let Some(date) = latest.last_modified_date() {
+            their_description.push_str(&format!(
+                ", changed {}",
+                aws::parameter_store::format_timestamp(*date)
+            ));
+        }
+        if let Some(user) = latest.last_modified_user() {
+            their_description.push_str(&format!(" by {}", user));
+        }
     }
 
-    aws::parameter_store::push_updated_parameters(original_parameters, after_edit, operation_mode)
-        .unwrap();
+    vec![
+        format!("{}:", key),
+        format!("  base:   {}", describe(base.parameters().get(key))),
+        format!("  theirs: {}", their_description),
+        format!("  mine:   {}", describe(mine.parameters().get(key))),
+    ]
 }
@@ -0,0 +1,64 @@
+use std::process::{self, Command};
+
+use crate::aws;
+use crate::cli;
+use crate::environment;
+
+use environment::env_names::EnvNaming;
+
+// The `exec` command runs a program with the parameters under one or
+// more prefixes added to its environment.  Values are handed straight
+// to the child process, and never written to disk.
+pub fn run(args: cli::Keez, prefixes: Vec<String>, naming: EnvNaming, command: Vec<String>) {
+    let backend = args.untagged_backend().unwrap();
+
+    let collections: Vec<aws::parameter_store::ParameterCollection> = prefixes
+        .iter()
+        .map(|prefix| {
+            aws::parameter_store::get_parameters_by_path(&*backend, prefix.clone()).unwrap()
+        })
+        .collect();
+
+    let variables = match environment::env_names::environment_from(&collections, naming) {
+        Ok(variables) => variables,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if args.debug {
+        eprintln!("Setting environment variables:");
+        for name in variables.keys() {
+            eprintln!("  {}", name);
+        }
+    }
+
+    let (program, arguments) = command.split_first().unwrap();
+    let mut child = Command::new(program);
+    child.args(arguments).envs(&variables);
+
+    run_command(child, program);
+}
+
+// On Unix, replace keez with the child process, so that signals and
+// the exit status are the child's own.
+#[cfg(unix)]
+fn run_command(mut child: Command, program: &str) -> ! {
+    use std::os::unix::process::CommandExt;
+
+    let error = child.exec();
+    eprintln!("Failed to run {}: {}", program, error);
+    process::exit(127);
+}
+
+#[cfg(not(unix))]
+fn run_command(mut child: Command, program: &str) -> ! {
+    match child.status() {
+        Ok(status) => process::exit(status.code().unwrap_or(1)),
+        Err(error) => {
+            eprintln!("Failed to run {}: {}", program, error);
+            process::exit(127);
+        }
+    }
+}
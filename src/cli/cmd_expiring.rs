@@ -0,0 +1,53 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::aws;
+use crate::cli;
+
+// The `expiring` command lists parameters under a prefix whose
+// Expiration policy falls within the next few days, soonest first.
+pub fn run(args: cli::Keez, prefix: String, days: i64) {
+    let backend = args.untagged_backend().unwrap();
+
+    let parameters =
+        aws::parameter_store::get_parameters_by_path(&*backend, prefix.clone()).unwrap();
+
+    let now = Utc::now();
+    let horizon = now + Duration::days(days);
+
+    let mut expiring: Vec<(DateTime<Utc>, &String)> = Vec::new();
+    for (key, param) in parameters.parameters() {
+        for policy in &param.attributes().policies {
+            match policy.expiration() {
+                Some(time) if time <= horizon => expiring.push((time, key)),
+                _ => {}
+            }
+        }
+    }
+    expiring.sort();
+
+    if expiring.is_empty() {
+        eprintln!(
+            "No parameters under {} expire within {} days.",
+            prefix, days
+        );
+        return;
+    }
+
+    for (time, key) in expiring {
+        let remaining = time - now;
+        let when = if remaining < Duration::zero() {
+            "expired".to_string()
+        } else if remaining < Duration::days(1) {
+            format!("in {} hours", remaining.num_hours())
+        } else {
+            format!("in {} days", remaining.num_days())
+        };
+
+        println!(
+            "{}  {:>12}  {}",
+            time.format("%Y-%m-%d %H:%M:%S UTC"),
+            when,
+            key
+        );
+    }
+}
@@ -1,22 +1,66 @@
 use std::env;
 use std::fs;
 use std::path::Path;
+use std::process;
 
 use crate::aws;
+use crate::backend::parameter_backend::StoreLocation;
 use crate::cli;
+use crate::environment;
 use crate::flags;
 use crate::secrets;
 
+use environment::env_formats::EnvFormat;
+use environment::env_names::EnvNaming;
 use flags::operation_mode::OperationMode;
+use secrets::export_file::Encryption;
+use secrets::keychain_access::KeyName;
+use secrets::passphrase::PassphraseSource;
+use secrets::recipients::Recipient;
 
+#[allow(clippy::too_many_arguments)]
 pub fn run(
     args: cli::Keez,
-    export_filename: std::path::PathBuf,
+    export_filename: Option<std::path::PathBuf>,
     insecure_output: bool,
     source: String,
+    label: Option<String>,
+    format: Option<EnvFormat>,
+    naming: EnvNaming,
+    passphrase: Option<PassphraseSource>,
+    recipients: Vec<Recipient>,
+    key_name: Option<KeyName>,
     operation_mode: OperationMode,
 ) {
-    let ps = aws::parameter_store::get_parameters_by_path(source, args.debug);
+    if export_filename.is_some() == format.is_some() {
+        eprintln!("Please specify exactly one of --export-filename and --format.");
+        process::exit(2);
+    }
+    if format.is_some() && (passphrase.is_some() || !recipients.is_empty() || key_name.is_some()) {
+        eprintln!(
+            "--format writes plaintext, so --passphrase, --recipient and --key-name don't apply."
+        );
+        process::exit(2);
+    }
+    if passphrase.is_some() && !recipients.is_empty() {
+        eprintln!("Please specify either --passphrase or --recipient, not both.");
+        process::exit(2);
+    }
+    if key_name.is_some() && (passphrase.is_some() || !recipients.is_empty()) {
+        eprintln!("--key-name only applies to the keychain key, not --passphrase or --recipient.");
+        process::exit(2);
+    }
+
+    // Environment formats leave tags out, so don't fetch them.
+    let backend = if format.is_some() {
+        args.untagged_backend().unwrap()
+    } else {
+        args.backend().unwrap()
+    };
+    let ps = match &label {
+        Some(label) => aws::parameter_store::get_parameters_by_label(&*backend, source, label),
+        None => aws::parameter_store::get_parameters_by_path(&*backend, source),
+    };
 
     if args.debug {
         eprintln!("Raw output from Parameter Store:");
@@ -30,20 +74,55 @@ pub fn run(
         unwrapped_parameterblob.parameters().len()
     );
 
-    let yaml_blob = serde_yaml::to_string(&unwrapped_parameterblob).unwrap();
+    // Writing to stdout in one of the environment formats is meant for
+    // feeding other tools, so it skips the encryption altogether.
+    if let Some(format) = format {
+        let variables =
+            match environment::env_names::environment_from(&[unwrapped_parameterblob], naming) {
+                Ok(variables) => variables,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(2);
+                }
+            };
+        print!(
+            "{}",
+            environment::env_formats::render(&variables, format).unwrap()
+        );
+        return;
+    }
 
     if insecure_output {
+        let yaml_blob = serde_yaml::to_string(&unwrapped_parameterblob).unwrap();
         eprintln!("{}", yaml_blob);
     }
 
-    if args.debug {
-        let key = secrets::keychain_access::get_symmetric_key();
-        eprintln!("Found symmetric key = {:?}", key);
-    }
+    let encryption = match passphrase {
+        None if !recipients.is_empty() => Encryption::Recipients(recipients),
+        Some(source) => Encryption::Passphrase(secrets::passphrase::read(&source, true).unwrap()),
+        None => {
+            let key_name = key_name.unwrap_or_default();
+            if args.debug {
+                let key = secrets::keychain_access::get_symmetric_key(&key_name);
+                eprintln!("Found symmetric key {} = {:?}", key_name, key);
+            }
 
-    let encrypted_form = secrets::symmetric_store::encrypt(yaml_blob).unwrap();
+            Encryption::Keychain(key_name)
+        }
+    };
+
+    // Looking up the location can mean a call to AWS, which a dry run
+    // has no use for, as nothing gets written.
+    let location = if operation_mode == OperationMode::ReadWrite {
+        backend.location()
+    } else {
+        StoreLocation::default()
+    };
+    let encrypted_form =
+        secrets::export_file::encode(&unwrapped_parameterblob, &encryption, &location).unwrap();
 
     // Create a path to the desired file
+    let export_filename = export_filename.unwrap();
     let path = Path::new(&export_filename);
     let absolute_path = if path.is_absolute() {
         path.to_path_buf()
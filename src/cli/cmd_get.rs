@@ -0,0 +1,96 @@
+use std::process;
+
+use crate::aws;
+use crate::cli;
+
+use aws::parameter_store::{timestamp_to_datetime, Parameter};
+
+// The `get` command prints a single parameter's value to stdout, for
+// use in shell scripts.  With `json`, the value is printed along with
+// its type, attributes and version information.
+pub fn run(args: cli::Keez, name: String, version: Option<i64>, label: Option<String>, json: bool) {
+    if version.is_some() && label.is_some() {
+        eprintln!("Please specify at most one of --version and --label.");
+        process::exit(2);
+    }
+
+    let backend = args.backend().unwrap();
+
+    let current = match backend.get_parameter(&name).unwrap() {
+        Some(current) => current,
+        None => {
+            eprintln!("Parameter {} not found.", name);
+            process::exit(1);
+        }
+    };
+
+    // Labels, and anything but the latest version, can only be found
+    // in the parameter's history.
+    let history = if version.is_some() || label.is_some() || json {
+        backend.parameter_history(&name).unwrap()
+    } else {
+        Vec::new()
+    };
+
+    let selected = match (version, &label) {
+        (Some(number), _) => history.iter().find(|v| *v.version() == number),
+        (_, Some(label)) => aws::parameter_store::labelled_version(&history, label),
+        _ => history
+            .iter()
+            .find(|v| Some(*v.version()) == *current.version()),
+    };
+
+    let parameter = match (version.is_some() || label.is_some(), selected) {
+        (false, _) => current.clone(),
+        (true, Some(selected)) => {
+            // Tags aren't versioned, so report the current ones.
+            let mut attributes = selected.parameter().attributes().clone();
+            attributes.tags = current.attributes().tags.clone();
+            selected.to_parameter().with_attributes(attributes)
+        }
+        (true, None) => {
+            eprintln!("Parameter {} has no such version.", name);
+            process::exit(1);
+        }
+    };
+
+    if args.debug {
+        eprintln!("Found parameter:");
+        eprintln!("{:?}", parameter);
+    }
+
+    if json {
+        let labels = selected.map(|v| v.labels().clone()).unwrap_or_default();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&to_json(&name, &parameter, labels)).unwrap()
+        );
+    } else {
+        println!("{}", parameter.value());
+    }
+}
+
+// Describe a parameter as a JSON object: the same fields as in the
+// YAML keez uses elsewhere, plus the name and version information.
+fn to_json(name: &str, parameter: &Parameter, labels: Vec<String>) -> serde_json::Value {
+    let mut object = serde_json::to_value(parameter).unwrap();
+
+    if let Some(fields) = object.as_object_mut() {
+        fields.insert("name".to_string(), name.into());
+        if let Some(version) = parameter.version() {
+            fields.insert("version".to_string(), (*version).into());
+        }
+        if let Some(date) = parameter.last_modified_date() {
+            fields.insert(
+                "last_modified_date".to_string(),
+                timestamp_to_datetime(*date).to_rfc3339().into(),
+            );
+        }
+        if let Some(user) = parameter.last_modified_user() {
+            fields.insert("last_modified_user".to_string(), user.clone().into());
+        }
+        fields.insert("labels".to_string(), labels.into());
+    }
+
+    object
+}
@@ -0,0 +1,82 @@
+use std::process;
+
+use crate::aws;
+use crate::cli;
+
+use aws::parameter_diff::describe_modification;
+use aws::parameter_store::format_timestamp;
+
+// The `history` command lists every stored version of a key, or of
+// each key under a prefix, along with what changed in each version.
+pub fn run(args: cli::Keez, name: String, recursive: bool, reveal: bool) {
+    let backend = args.untagged_backend().unwrap();
+
+    let keys = aws::parameter_store::select_keys(&*backend, &name, recursive).unwrap();
+    if keys.is_empty() {
+        eprintln!("No parameters found under {}.", name);
+        process::exit(1);
+    }
+
+    let mut missing = 0;
+    for key in keys {
+        let history = match backend.parameter_history(&key) {
+            Ok(history) => history,
+            Err(e) => {
+                eprintln!("{}: can't fetch history: {}", key, e);
+                missing += 1;
+                continue;
+            }
+        };
+        if history.is_empty() {
+            eprintln!("{}: no history found.", key);
+            missing += 1;
+            continue;
+        }
+
+        println!("{}", key);
+        let mut previous: Option<&aws::parameter_store::Parameter> = None;
+        for version in &history {
+            let labels = if version.labels().is_empty() {
+                String::new()
+            } else {
+                format!("  [{}]", version.labels().join(", "))
+            };
+            println!(
+                "  v{}  {}  {}{}",
+                version.version(),
+                version
+                    .last_modified_date()
+                    .map(format_timestamp)
+                    .unwrap_or_else(|| "(unknown date)".to_string()),
+                version
+                    .last_modified_user()
+                    .as_deref()
+                    .unwrap_or("(unknown user)"),
+                labels
+            );
+
+            let current = version.parameter();
+            match previous {
+                None => println!(
+                    "      created ({}): {}",
+                    current.parameter_type(),
+                    current.display_value(reveal)
+                ),
+                Some(old) => {
+                    let changes = describe_modification(old, current, reveal);
+                    if changes.is_empty() {
+                        println!("      no changes");
+                    }
+                    for change in changes {
+                        println!("      {}", change);
+                    }
+                }
+            }
+            previous = Some(current);
+        }
+    }
+
+    if missing > 0 {
+        process::exit(1);
+    }
+}
@@ -1,6 +1,6 @@
 use std::env;
-use std::fs;
 use std::path::Path;
+use std::process;
 
 use crate::aws;
 use crate::cli;
@@ -8,13 +8,23 @@ use crate::editor;
 use crate::flags;
 use crate::secrets;
 
+use aws::parameter_diff::ParameterDiff;
+use aws::parameter_store::{ParameterCollection, ParameterType};
+use aws::plaintext_import::InputFormat;
 use flags::operation_mode::OperationMode;
+use secrets::keychain_access::KeyName;
+use secrets::passphrase::PassphraseSource;
 
+#[allow(clippy::too_many_arguments)]
 pub fn run(
     args: cli::Keez,
     import_filename: std::path::PathBuf,
     destination: String,
     edit: bool,
+    input_format: Option<InputFormat>,
+    default_type: ParameterType,
+    passphrase: Option<PassphraseSource>,
+    key_name: Option<KeyName>,
     operation_mode: OperationMode,
 ) {
     // Create a path to the desired file
@@ -25,25 +35,30 @@ pub fn run(
         env::current_dir().unwrap().join(path)
     };
 
-    eprintln!(
-        "Reading exported parameters from {}... ",
-        absolute_path.display()
-    );
-
-    // Open file read-only.
-    // Read the file contents for decrypting.
-    let encrypted_blob: Vec<u8> = fs::read(absolute_path).unwrap();
-
-    let raw_yaml = secrets::symmetric_store::decrypt(encrypted_blob).unwrap();
-
-    if args.debug {
-        eprintln!("Read YAML from encrypted file:");
-        eprintln!("{}", raw_yaml);
-    }
-
-    // Deserialize it back to a Rust type.
-    let deserialized: aws::parameter_store::ParameterCollection =
-        serde_yaml::from_str(&raw_yaml).unwrap();
+    let deserialized = match input_format {
+        Some(format) => {
+            eprintln!(
+                "Reading plaintext parameters from {}... ",
+                absolute_path.display()
+            );
+            aws::plaintext_import::read(&absolute_path, Some(format), default_type).unwrap()
+        }
+        None => {
+            eprintln!(
+                "Reading exported parameters from {}... ",
+                absolute_path.display()
+            );
+            let passphrase =
+                passphrase.map(|source| secrets::passphrase::read(&source, false).unwrap());
+            secrets::export_file::read(
+                &absolute_path,
+                passphrase.as_deref(),
+                key_name.as_ref(),
+                args.debug,
+            )
+            .unwrap()
+        }
+    };
 
     eprintln!("Imported blob contains the following keys:");
     for (key, _param) in deserialized.parameters() {
@@ -57,12 +72,23 @@ pub fn run(
         eprintln!("{:?}", deserialized);
     }
 
-    let mut rerooted = aws::parameter_store::reroot_parameters(deserialized, destination).unwrap();
+    let mut rerooted =
+        aws::parameter_store::reroot_parameters(deserialized, destination.clone()).unwrap();
 
     if edit {
         rerooted =
             editor::edit_loop::interactive_edit_parameters(rerooted.clone(), args.debug).unwrap();
     }
 
-    aws::parameter_store::push_new_parameters(rerooted, operation_mode).unwrap();
+    let plan = ParameterDiff::between_absolute(&ParameterCollection::new(destination), &rerooted);
+    if !args.confirm_plan(&plan) {
+        eprintln!("Aborted, nothing was imported.");
+        return;
+    }
+
+    let backend = args.backend().unwrap();
+    if let Err(e) = aws::parameter_store::push_new_parameters(&*backend, rerooted, operation_mode) {
+        eprintln!("Error: {}  Use `keez edit` to update existing keys.", e);
+        process::exit(1);
+    }
 }
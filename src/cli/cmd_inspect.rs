@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+use std::process;
+
+use crate::cli;
+use crate::secrets;
+
+use secrets::export_file::EncryptionScheme;
+
+// The `inspect` command describes an export file from its header,
+// without decrypting it, so it works without any keys at hand.
+pub fn run(_args: cli::Keez, file: PathBuf) {
+    let header = match secrets::export_file::read_header(&file) {
+        Ok(Some(header)) => header,
+        Err(e) => {
+            eprintln!("Can't read {}: {}", file.display(), e);
+            process::exit(1);
+        }
+        Ok(None) => {
+            println!("File:            {}", file.display());
+            println!("Format version:  0 (no header, written by an older keez)");
+            println!("Encryption:      keychain or passphrase");
+            return;
+        }
+    };
+
+    println!("File:            {}", file.display());
+    println!("Format version:  {}", header.format_version);
+    match header.encryption {
+        EncryptionScheme::Keychain => println!(
+            "Encryption:      keychain, key {} ({})",
+            header.key_name.as_deref().unwrap_or("default"),
+            header.key_id.as_deref().unwrap_or("unknown")
+        ),
+        EncryptionScheme::Passphrase => println!("Encryption:      passphrase"),
+        EncryptionScheme::Recipients => {
+            println!("Encryption:      recipients");
+            for recipient in &header.recipients {
+                println!("                 - {}", recipient);
+            }
+        }
+    }
+    println!("Created:         {}", header.created);
+    println!("Source prefix:   {}", header.source_prefix);
+    println!(
+        "Source account:  {}",
+        header.source_account.as_deref().unwrap_or("unknown")
+    );
+    println!(
+        "Source region:   {}",
+        header.source_region.as_deref().unwrap_or("unknown")
+    );
+    println!("Parameters:      {}", header.parameter_count);
+}
@@ -0,0 +1,257 @@
+use std::error;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use mktemp::Temp;
+
+use crate::cli;
+use crate::flags::operation_mode::OperationMode;
+use crate::secrets;
+
+use secrets::keychain_access;
+use secrets::keychain_access::KeyName;
+use secrets::symmetric_store::{escrow_decode, escrow_encode, fingerprint};
+
+// The `key` commands look after the keychain keys which exports are
+// encrypted with by default.
+pub fn run(
+    args: cli::Keez,
+    key_name: KeyName,
+    command: cli::KeyCommand,
+    operation_mode: OperationMode,
+) {
+    match command {
+        cli::KeyCommand::Fingerprint => {
+            let key = existing_key(&key_name);
+            println!("{}", fingerprint(&key));
+        }
+        cli::KeyCommand::Export => {
+            let key = existing_key(&key_name);
+            eprintln!("Anyone holding this can decrypt your keychain-encrypted exports.");
+            println!("{}", escrow_encode(&key));
+        }
+        cli::KeyCommand::Import { encoded_key } => {
+            let encoded_key = encoded_key.unwrap_or_else(|| {
+                let mut input = String::new();
+                io::stdin().read_to_string(&mut input).unwrap();
+                input
+            });
+            let key = match escrow_decode(&encoded_key) {
+                Ok(key) => key,
+                Err(e) => {
+                    eprintln!("Can't import key: {}", e);
+                    process::exit(1);
+                }
+            };
+            replace_key(&args, &key_name, &key, &operation_mode);
+        }
+        cli::KeyCommand::Rotate => {
+            if operation_mode == OperationMode::ReadOnly {
+                eprintln!(
+                    "[DRY-RUN] Would replace your keychain key {} with a new one.",
+                    key_name
+                );
+                return;
+            }
+            let current = keychain_access::find_symmetric_key(&key_name).unwrap();
+            if current.is_some() && !confirm_replace(&args, &key_name) {
+                return;
+            }
+            if let Some(current) = current {
+                retire(&key_name, &current);
+            }
+            let key = keychain_access::set_symmetric_key(&key_name).unwrap();
+            eprintln!(
+                "Your keychain key {} is now {}.",
+                key_name,
+                fingerprint(&key)
+            );
+        }
+        cli::KeyCommand::Reencrypt { old_key, files } => {
+            let old_keys = match old_key {
+                Some(encoded_key) => match escrow_decode(&encoded_key) {
+                    Ok(key) => vec![(fingerprint(&key), key)],
+                    Err(e) => {
+                        eprintln!("Can't use --old-key: {}", e);
+                        process::exit(2);
+                    }
+                },
+                None => keychain_access::find_retired_symmetric_keys(&key_name).unwrap(),
+            };
+            if old_keys.is_empty() {
+                eprintln!(
+                    "You have no retired keys named {}, please pass --old-key.",
+                    key_name
+                );
+                process::exit(2);
+            }
+            reencrypt(&key_name, &old_keys, &files, &operation_mode);
+        }
+    }
+}
+
+// Look up the key for a command which only uses it, without creating
+// one, so a mistyped --key-name doesn't leave a stray key behind.
+fn existing_key(name: &KeyName) -> String {
+    match keychain_access::find_symmetric_key(name).unwrap() {
+        Some(key) => key,
+        None => {
+            eprintln!("You have no keychain key named {}.", name);
+            process::exit(1);
+        }
+    }
+}
+
+// Keep a key which is being replaced, and print it, so there's a copy
+// outside the keychain too.
+fn retire(name: &KeyName, key: &str) {
+    keychain_access::retire_symmetric_key(name, &fingerprint(key), key).unwrap();
+    eprintln!(
+        "Your old key {} is kept as a retired key.  Back it up as well, here it is:",
+        fingerprint(key)
+    );
+    println!("{}", escrow_encode(key));
+}
+
+// Store `key` as the keychain key, retiring the current one.
+fn replace_key(args: &cli::Keez, name: &KeyName, key: &str, operation_mode: &OperationMode) {
+    let current = keychain_access::find_symmetric_key(name).unwrap();
+    if current.as_deref() == Some(key) {
+        eprintln!(
+            "That is already your keychain key {}, {}.",
+            name,
+            fingerprint(key)
+        );
+        return;
+    }
+
+    if *operation_mode == OperationMode::ReadOnly {
+        eprintln!(
+            "[DRY-RUN] Would replace your keychain key {} with {}.",
+            name,
+            fingerprint(key)
+        );
+        return;
+    }
+
+    if let Some(current) = current {
+        if !confirm_replace(args, name) {
+            return;
+        }
+        retire(name, &current);
+    }
+    keychain_access::store_symmetric_key(name, key).unwrap();
+    eprintln!("Your keychain key {} is now {}.", name, fingerprint(key));
+}
+
+fn confirm_replace(args: &cli::Keez, name: &KeyName) -> bool {
+    eprintln!("Exports made with your current key will need `keez key reencrypt` to be imported.");
+    if !args.confirm(&format!("Really replace your keychain key {}?", name)) {
+        eprintln!("Aborted, your keychain key was kept.");
+        return false;
+    }
+    true
+}
+
+// Move each file over from one of `old_keys` to the current keychain
+// key `name`, carrying on past files which can't be re-encrypted.
+fn reencrypt(
+    name: &KeyName,
+    old_keys: &[(String, String)],
+    files: &[PathBuf],
+    operation_mode: &OperationMode,
+) {
+    let new_key = existing_key(name);
+    let new_id = fingerprint(&new_key);
+    let mut failures = 0;
+
+    for file in files {
+        let header = secrets::export_file::read_header(file).unwrap_or(None);
+        if header.and_then(|header| header.key_id).as_deref() == Some(&new_id[..]) {
+            eprintln!("{} already uses key {}, skipping.", file.display(), new_id);
+            continue;
+        }
+
+        let reencrypted = match reencrypt_file(file, old_keys, &new_key, name) {
+            Ok(reencrypted) => reencrypted,
+            Err(e) => {
+                eprintln!("Can't re-encrypt {}: {}", file.display(), e);
+                failures += 1;
+                continue;
+            }
+        };
+
+        if *operation_mode == OperationMode::ReadWrite {
+            eprint!("Re-encrypting {} with key {}... ", file.display(), new_id);
+            match replace_file(file, &reencrypted) {
+                Ok(()) => eprintln!("done."),
+                Err(e) => {
+                    eprintln!("failed: {}", e);
+                    failures += 1;
+                }
+            }
+        } else {
+            eprintln!(
+                "[DRY-RUN] Would re-encrypt {} with key {}.",
+                file.display(),
+                new_id
+            );
+        }
+    }
+
+    if failures > 0 {
+        eprintln!("{} file(s) could not be re-encrypted.", failures);
+        process::exit(1);
+    }
+}
+
+// Re-encrypt with the old key the header names, or for files without
+// one, with whichever old key works.
+fn reencrypt_file(
+    file: &Path,
+    old_keys: &[(String, String)],
+    new_key: &str,
+    new_key_name: &KeyName,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let data = fs::read(file)?;
+    let header = secrets::export_file::read_header(file)?;
+
+    if let Some(key_id) = header.and_then(|header| header.key_id) {
+        let old_key = old_keys
+            .iter()
+            .find(|(fingerprint, _)| *fingerprint == key_id)
+            .map(|(_, key)| key)
+            .ok_or_else(|| format!("encrypted with key {}, which isn't a retired key", key_id))?;
+        return secrets::export_file::reencrypt(&data, old_key, new_key, new_key_name);
+    }
+
+    let mut last_error = None;
+    for (_, old_key) in old_keys.iter().rev() {
+        match secrets::export_file::reencrypt(&data, old_key, new_key, new_key_name) {
+            Ok(reencrypted) => return Ok(reencrypted),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| "no old key to decrypt with".into()))
+}
+
+// Write the new contents next to `file` first, and only then rename
+// them over it, so a crash or a full disk can't leave the only copy of
+// an export half-written.  The new file keeps the old one's mode.
+fn replace_file(file: &Path, contents: &[u8]) -> Result<(), Box<dyn error::Error>> {
+    let directory = match file.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    let permissions = fs::metadata(file)?.permissions();
+    let temp_file = Temp::new_file_in(directory)?;
+
+    fs::write(&temp_file, contents)?;
+    fs::set_permissions(&temp_file, permissions)?;
+    fs::rename(&temp_file, file)?;
+    temp_file.release();
+    Ok(())
+}
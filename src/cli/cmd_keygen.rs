@@ -0,0 +1,31 @@
+use crate::cli;
+use crate::secrets;
+
+use secrets::recipients::Identity;
+
+// The `keygen` command creates the local identity which recipient-
+// encrypted exports are decrypted with, and prints its public key.
+pub fn run(args: cli::Keez, force: bool) {
+    if let Some(existing) = secrets::recipients::local_identity().unwrap() {
+        if !force {
+            eprintln!("You already have an identity, its public key is:");
+            println!("{}", existing.recipient());
+            return;
+        }
+
+        eprintln!(
+            "Exports encrypted to your current identity can't be decrypted once it's replaced."
+        );
+        if !args.confirm("Really replace your identity?") {
+            eprintln!("Aborted, your identity was kept.");
+            return;
+        }
+    }
+
+    let identity = Identity::generate().unwrap();
+    secrets::keychain_access::set_identity(&identity.encoded()).unwrap();
+
+    eprintln!("Generated a new identity and stored it in your system keychain.");
+    eprintln!("Share this public key with whoever wants to send you parameters:");
+    println!("{}", identity.recipient());
+}
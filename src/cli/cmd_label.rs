@@ -0,0 +1,110 @@
+use std::process;
+
+use crate::aws;
+use crate::cli;
+use crate::flags;
+
+use flags::operation_mode::OperationMode;
+
+// The `label` command attaches labels to (or removes them from) a
+// version of a parameter, or of every parameter under a prefix.  We
+// use labels to pin the values a release should read.
+pub fn run(
+    args: cli::Keez,
+    name: String,
+    labels: Vec<String>,
+    recursive: bool,
+    version: Option<i64>,
+    remove: bool,
+    operation_mode: OperationMode,
+) {
+    if !remove {
+        for label in &labels {
+            aws::parameter_store::check_label(label).unwrap();
+        }
+    }
+
+    let backend = args.untagged_backend().unwrap();
+    if remove && !backend.can_remove_labels() {
+        eprintln!("Removing labels isn't supported with AWS Parameter Store yet, use the AWS console or CLI instead.");
+        process::exit(2);
+    }
+
+    let keys = aws::parameter_store::select_keys(&*backend, &name, recursive).unwrap();
+
+    // Work out which version of each key to touch up front, so that
+    // the user can see the whole plan before confirming.
+    let mut plan: Vec<(String, i64)> = Vec::new();
+    for key in keys {
+        let history = backend.parameter_history(&key).unwrap();
+
+        let targets: Vec<i64> = if remove {
+            history
+                .iter()
+                .filter(|v| version.is_none() || version == Some(*v.version()))
+                .filter(|v| v.labels().iter().any(|l| labels.contains(l)))
+                .map(|v| *v.version())
+                .collect()
+        } else {
+            history
+                .iter()
+                .rev()
+                .find(|v| version.is_none() || version == Some(*v.version()))
+                .map(|v| *v.version())
+                .into_iter()
+                .collect()
+        };
+
+        if targets.is_empty() {
+            eprintln!("{}: no matching version, skipping.", key);
+        }
+        for target in targets {
+            plan.push((key.clone(), target));
+        }
+    }
+
+    if plan.is_empty() {
+        eprintln!("Nothing to do.");
+        return;
+    }
+
+    let (verb, progress, action) = if remove {
+        ("removed from", "Removing labels from", "remove labels from")
+    } else {
+        ("attached to", "Labelling", "label")
+    };
+    eprintln!(
+        "The labels {} will be {} these {} versions:",
+        labels.join(", "),
+        verb,
+        plan.len()
+    );
+    for (key, target) in &plan {
+        eprintln!("  - {} (version {})", key, target);
+    }
+
+    if operation_mode == OperationMode::ReadWrite && !args.confirm("Apply these changes?") {
+        eprintln!("Aborted, nothing was changed.");
+        return;
+    }
+
+    for (key, target) in plan {
+        if operation_mode == OperationMode::ReadWrite {
+            eprintln!("{} key {} version {}...", progress, key, target);
+            if remove {
+                backend
+                    .unlabel_parameter_version(&key, target, &labels)
+                    .unwrap();
+            } else {
+                backend
+                    .label_parameter_version(&key, Some(target), &labels)
+                    .unwrap();
+            }
+        } else {
+            eprintln!(
+                "[DRY-RUN] Would {} key {} version {}...",
+                action, key, target
+            );
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+use crate::aws;
+use crate::cli;
+use crate::flags;
+
+use aws::parameter_store::{format_timestamp, timestamp_to_datetime, Parameter};
+use flags::list_format::ListFormat;
+
+// A directory in the parameter hierarchy.  In Parameter Store a name
+// can be a parameter and a directory at the same time, for instance
+// /app/db and /app/db/host.
+#[derive(Default)]
+struct Node<'a> {
+    parameter: Option<&'a Parameter>,
+    children: BTreeMap<String, Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    fn insert(&mut self, path: &[&str], parameter: &'a Parameter) {
+        match path.split_first() {
+            Some((first, rest)) => self
+                .children
+                .entry(first.to_string())
+                .or_default()
+                .insert(rest, parameter),
+            None => self.parameter = Some(parameter),
+        }
+    }
+
+    // The number of parameters underneath this node, not counting
+    // the node itself.
+    fn descendants(&self) -> usize {
+        self.children
+            .values()
+            .map(|child| child.parameter.iter().count() + child.descendants())
+            .sum()
+    }
+}
+
+// The `ls` command shows which parameters exist under a prefix, along
+// with their type, tier, version and last modification, but never
+// their values.
+pub fn run(
+    args: cli::Keez,
+    prefix: String,
+    non_recursive: bool,
+    depth: Option<usize>,
+    output: ListFormat,
+) {
+    let backend = args.untagged_backend().unwrap();
+
+    let parameters = backend.list_parameters(&prefix, !non_recursive).unwrap();
+    if parameters.is_empty() {
+        eprintln!("No parameters found under {}.", prefix);
+        return;
+    }
+
+    let hierarchy = format!("{}/", prefix.trim_end_matches('/'));
+    let mut entries: Vec<(&String, Vec<&str>, &Parameter)> = parameters
+        .iter()
+        .map(|(name, param)| {
+            let relative = name.strip_prefix(&hierarchy).unwrap_or(name);
+            (name, relative.split('/').collect(), param)
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    match output {
+        ListFormat::Tree => {
+            let mut root = Node::default();
+            for (_, path, param) in &entries {
+                root.insert(path, param);
+            }
+
+            let mut rows = vec![vec![prefix.clone()]];
+            walk(&root, "", 1, depth, &mut rows);
+            print_table(rows);
+        }
+        ListFormat::Flat => {
+            let rows = entries
+                .iter()
+                .filter(|(_, path, _)| within(path, depth))
+                .map(|(name, _, param)| {
+                    let mut row = vec![name.to_string()];
+                    row.extend(columns(param));
+                    row
+                })
+                .collect();
+            print_table(rows);
+        }
+        ListFormat::Json => {
+            let objects: Vec<serde_json::Value> = entries
+                .iter()
+                .filter(|(_, path, _)| within(path, depth))
+                .map(|(name, _, param)| to_json(name, param))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&objects).unwrap());
+        }
+    }
+}
+
+fn within(path: &[&str], depth: Option<usize>) -> bool {
+    match depth {
+        Some(depth) => path.len() <= depth,
+        None => true,
+    }
+}
+
+// Add a row for every child of `node`, drawing the branches of the
+// tree as we go.  Directories deeper than `max_depth` are collapsed
+// into a count of the parameters inside them.
+fn walk(
+    node: &Node,
+    indent: &str,
+    depth: usize,
+    max_depth: Option<usize>,
+    rows: &mut Vec<Vec<String>>,
+) {
+    let last_index = node.children.len().saturating_sub(1);
+
+    for (index, (name, child)) in node.children.iter().enumerate() {
+        let (branch, continuation) = if index == last_index {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        let collapsed =
+            matches!(max_depth, Some(max) if depth >= max) && !child.children.is_empty();
+
+        let mut label = format!("{}{}{}", indent, branch, name);
+        if !child.children.is_empty() {
+            label.push('/');
+        }
+        if collapsed {
+            label.push_str(&format!(" ({} more)", child.descendants()));
+        }
+
+        let mut row = vec![label];
+        if let Some(param) = child.parameter {
+            row.extend(columns(param));
+        }
+        rows.push(row);
+
+        if !collapsed {
+            walk(
+                child,
+                &format!("{}{}", indent, continuation),
+                depth + 1,
+                max_depth,
+                rows,
+            );
+        }
+    }
+}
+
+fn columns(param: &Parameter) -> Vec<String> {
+    vec![
+        param.parameter_type().to_string(),
+        param
+            .attributes()
+            .tier
+            .map_or("Standard".to_string(), |tier| tier.to_string()),
+        param
+            .version()
+            .map_or("-".to_string(), |version| format!("v{}", version)),
+        param
+            .last_modified_date()
+            .map_or("-".to_string(), format_timestamp),
+    ]
+}
+
+fn to_json(name: &str, param: &Parameter) -> serde_json::Value {
+    serde_json::json!({
+        "name": name,
+        "type": param.parameter_type().to_string(),
+        "tier": param
+            .attributes()
+            .tier
+            .map_or("Standard".to_string(), |tier| tier.to_string()),
+        "version": param.version(),
+        "last_modified_date": param
+            .last_modified_date()
+            .map(|date| timestamp_to_datetime(date).to_rfc3339()),
+        "last_modified_user": param.last_modified_user(),
+    })
+}
+
+// Print rows of cells as left-aligned columns.
+fn print_table(rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &rows {
+        for (index, cell) in row.iter().enumerate() {
+            let width = cell.chars().count();
+            match widths.get_mut(index) {
+                Some(existing) => *existing = (*existing).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    for row in rows {
+        let mut line = String::new();
+        for (index, cell) in row.iter().enumerate() {
+            line.push_str(cell);
+            if index + 1 < row.len() {
+                let padding = widths[index] - cell.chars().count() + 2;
+                line.push_str(&" ".repeat(padding));
+            }
+        }
+        println!("{}", line);
+    }
+}
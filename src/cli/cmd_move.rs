@@ -0,0 +1,39 @@
+use crate::aws;
+use crate::cli;
+use crate::flags;
+
+use flags::operation_mode::OperationMode;
+
+// The `move` command is a `copy` followed by a `delete` of the
+// originals, where the delete only happens if the copy fully
+// succeeded.
+pub fn run(args: cli::Keez, source: String, destination: String, operation_mode: OperationMode) {
+    let backend = args.backend().unwrap();
+
+    let parameters =
+        aws::parameter_store::get_parameters_by_path(&*backend, source.clone()).unwrap();
+
+    if parameters.parameters().is_empty() {
+        eprintln!("No parameters found under {}, nothing to do.", source);
+        return;
+    }
+
+    let mut keys: Vec<&String> = parameters.parameters().keys().collect();
+    keys.sort();
+
+    eprintln!("The following {} keys will be moved:", keys.len());
+    for key in keys {
+        eprintln!("  - {}", key);
+    }
+    eprintln!("\nWe'll rewrite the path prefix:");
+    eprintln!("  {} => {}\n", source, destination);
+
+    if operation_mode == OperationMode::ReadWrite && !args.confirm("Really move these parameters?")
+    {
+        eprintln!("Aborted, nothing was moved.");
+        return;
+    }
+
+    aws::parameter_store::move_parameters(&*backend, parameters, destination, operation_mode)
+        .unwrap();
+}
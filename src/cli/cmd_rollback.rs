@@ -0,0 +1,107 @@
+use chrono::{DateTime, Utc};
+use std::process;
+
+use crate::aws;
+use crate::cli;
+use crate::flags;
+
+use aws::parameter_diff::ParameterDiff;
+use aws::parameter_store::{ParameterCollection, ParameterVersion};
+use flags::operation_mode::OperationMode;
+
+// The `rollback` command restores a key, or each key under a prefix,
+// to an earlier version from its history.  Restoring writes a new
+// version with the old contents, so a rollback can itself be undone.
+pub fn run(
+    args: cli::Keez,
+    name: String,
+    recursive: bool,
+    to_version: Option<i64>,
+    to_time: Option<DateTime<Utc>>,
+    operation_mode: OperationMode,
+) {
+    if to_version.is_some() == to_time.is_some() {
+        eprintln!("Please specify exactly one of --to-version and --to-time.");
+        process::exit(2);
+    }
+
+    let backend = args.backend().unwrap();
+
+    let keys = aws::parameter_store::select_keys(&*backend, &name, recursive).unwrap();
+
+    let mut current = ParameterCollection::new("".to_string());
+    let mut restored = ParameterCollection::new("".to_string());
+
+    for key in keys {
+        let existing = match backend.get_parameter(&key).unwrap() {
+            Some(existing) => existing,
+            None => {
+                eprintln!("{} doesn't exist, skipping.", key);
+                continue;
+            }
+        };
+
+        let history = backend.parameter_history(&key).unwrap();
+        let target = match (to_version, to_time) {
+            (Some(version), _) => history.iter().find(|v| *v.version() == version),
+            (_, Some(time)) => version_at(&history, time),
+            _ => None,
+        };
+
+        let target = match target {
+            Some(target) => target,
+            None => {
+                eprintln!("{} has no version to roll back to, leaving it alone.", key);
+                continue;
+            }
+        };
+
+        // Tags aren't versioned, so keep the ones the key has now
+        // rather than wiping them.
+        let mut attributes = target.parameter().attributes().clone();
+        attributes.tags = existing.attributes().tags.clone();
+
+        restored.insert(
+            key.clone(),
+            target
+                .parameter()
+                .without_store_metadata()
+                .with_attributes(attributes),
+        );
+        current.insert(key, existing);
+    }
+
+    let diff = ParameterDiff::between_absolute(&current, &restored);
+
+    if args.debug {
+        eprintln!("Computed diff:");
+        eprintln!("{:?}", diff);
+    }
+
+    if diff.is_empty() {
+        eprintln!("Nothing to roll back.");
+        return;
+    }
+
+    aws::parameter_store::validate_changes(&diff).unwrap();
+
+    eprintln!("Planned rollback:");
+    if !args.confirm_plan(&diff) {
+        eprintln!("Aborted, nothing was changed.");
+        return;
+    }
+
+    aws::parameter_store::apply_diff(&*backend, &diff, "", operation_mode).unwrap();
+}
+
+// version_at finds the version which was current at `time`, that is,
+// the latest one written no later than `time`.  History is ordered
+// oldest first.
+fn version_at(history: &[ParameterVersion], time: DateTime<Utc>) -> Option<&ParameterVersion> {
+    let seconds = time.timestamp_millis() as f64 / 1000.0;
+
+    history
+        .iter()
+        .rev()
+        .find(|v| matches!(v.last_modified_date(), Some(date) if *date <= seconds))
+}
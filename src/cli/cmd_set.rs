@@ -0,0 +1,97 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process;
+
+use crate::aws;
+use crate::cli;
+use crate::flags;
+
+use aws::parameter_store::{Parameter, ParameterError, ParameterType};
+use flags::operation_mode::OperationMode;
+
+// The `set` command writes a single parameter, for use in shell
+// scripts.  The value comes from the command line, a file, or stdin.
+pub fn run(
+    args: cli::Keez,
+    name: String,
+    value: Option<String>,
+    from_file: Option<PathBuf>,
+    parameter_type: Option<ParameterType>,
+    overwrite: bool,
+    operation_mode: OperationMode,
+) {
+    aws::parameter_store::check_path(name.clone()).unwrap();
+
+    let value = match (value, from_file) {
+        (Some(_), Some(_)) => {
+            eprintln!("Please pass either <value> or --from-file, not both.");
+            process::exit(2);
+        }
+        (Some(value), None) => value,
+        (None, Some(path)) => strip_newline(fs::read_to_string(path).unwrap()),
+        (None, None) => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).unwrap();
+            strip_newline(input)
+        }
+    };
+
+    let backend = args.backend().unwrap();
+
+    let existing = backend.get_parameter(&name).unwrap();
+    if existing.is_some() && !overwrite {
+        eprintln!(
+            "Error: {}  Use --overwrite to replace it.",
+            ParameterError::ParameterAlreadyExists(name)
+        );
+        process::exit(1);
+    }
+
+    // When overwriting, keep everything about the parameter except its
+    // value, and its type if a new one was asked for.
+    let parameter = match &existing {
+        Some(existing) => Parameter::new(
+            value,
+            parameter_type
+                .unwrap_or(*existing.parameter_type())
+                .to_string(),
+        )
+        .with_attributes(existing.attributes().clone()),
+        None => Parameter::new(
+            value,
+            parameter_type
+                .unwrap_or(ParameterType::SecureString)
+                .to_string(),
+        ),
+    };
+
+    if args.debug {
+        eprintln!("Parameter to write:");
+        eprintln!("{:?}", parameter);
+    }
+
+    let (progress, action) = if existing.is_some() {
+        ("Updating", "update")
+    } else {
+        ("Creating", "create")
+    };
+    if operation_mode == OperationMode::ReadWrite {
+        eprintln!("{} key {}...", progress, name);
+        backend.put_parameter(&name, &parameter, overwrite).unwrap();
+    } else {
+        eprintln!("[DRY-RUN] Would {} key {}...", action, name);
+    }
+}
+
+// Values read from a file or a pipe usually end in a newline which
+// isn't meant to be part of the value.
+fn strip_newline(mut value: String) -> String {
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    value
+}
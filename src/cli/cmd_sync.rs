@@ -0,0 +1,64 @@
+use crate::aws;
+use crate::cli;
+use crate::flags;
+
+use aws::parameter_diff::ParameterDiff;
+use flags::operation_mode::OperationMode;
+
+// The `sync` command makes the tree under <destination> mirror the
+// tree under <source>.  Unlike `copy`, it overwrites values which
+// differ, and with `delete` it also removes keys which only exist in
+// the destination.
+pub fn run(
+    args: cli::Keez,
+    source: String,
+    destination: String,
+    delete: bool,
+    operation_mode: OperationMode,
+) {
+    let backend = args.backend().unwrap();
+
+    let source_parameters =
+        aws::parameter_store::get_parameters_by_path(&*backend, source.clone()).unwrap();
+    let destination_parameters =
+        aws::parameter_store::get_parameters_by_path(&*backend, destination.clone()).unwrap();
+
+    let rerooted =
+        aws::parameter_store::reroot_parameters(source_parameters, destination.clone()).unwrap();
+
+    let full_diff = ParameterDiff::between(&destination_parameters, &rerooted);
+    let diff = if delete {
+        full_diff.clone()
+    } else {
+        full_diff.without_removals()
+    };
+
+    if args.debug {
+        eprintln!("Computed diff:");
+        eprintln!("{:?}", full_diff);
+    }
+
+    let kept = full_diff.changes().len() - diff.changes().len();
+    if kept > 0 {
+        eprintln!(
+            "{} keys exist only under {} and will be kept.  Use --delete to remove them.",
+            kept, destination
+        );
+    }
+
+    if diff.is_empty() {
+        eprintln!(
+            "{} is already in sync with {}, nothing to do.",
+            destination, source
+        );
+        return;
+    }
+
+    eprintln!("Planned changes to {}:", destination);
+    if !args.confirm_plan(&diff) {
+        eprintln!("Aborted, nothing was changed.");
+        return;
+    }
+
+    aws::parameter_store::apply_diff(&*backend, &diff, &destination, operation_mode).unwrap();
+}
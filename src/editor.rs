@@ -1 +1,3 @@
 pub mod edit_loop;
+pub mod plan;
+pub mod prompt;
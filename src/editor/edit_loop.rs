@@ -27,7 +27,21 @@ pub fn interactive_edit_parameters(
     params: aws::parameter_store::ParameterCollection,
     debug: bool,
 ) -> Result<aws::parameter_store::ParameterCollection, Box<dyn error::Error>> {
-    let mut yaml_blob = serde_yaml::to_string(&params)?;
+    interactive_edit_parameters_with_header(params, "", debug)
+}
+
+/// Like `interactive_edit_parameters`, but put `header` at the top of
+/// the file as YAML comments, to give the user some context.
+pub fn interactive_edit_parameters_with_header(
+    params: aws::parameter_store::ParameterCollection,
+    header: &str,
+    debug: bool,
+) -> Result<aws::parameter_store::ParameterCollection, Box<dyn error::Error>> {
+    let mut yaml_blob = String::new();
+    for line in header.lines() {
+        yaml_blob.push_str(&format!("# {}\n", line));
+    }
+    yaml_blob.push_str(&serde_yaml::to_string(&params)?);
 
     loop {
         let new_yaml_blob = interactive_edit(yaml_blob.clone())?;
@@ -61,6 +75,40 @@ pub fn interactive_edit_parameters(
     }
 }
 
+/// Let the user narrow down a list of keys by deleting lines in their
+/// editor.  Returns the keys which are left over, in order.  Lines
+/// which don't match one of the original keys are ignored.
+pub fn interactive_select_keys(
+    keys: Vec<String>,
+    verb: &str,
+) -> Result<Vec<String>, Box<dyn error::Error>> {
+    let mut text = format!(
+        "# The following keys will be {}.  Remove the lines for any keys you\n\
+         # want to keep; lines starting with '#' are ignored.\n",
+        verb
+    );
+    for key in &keys {
+        text.push_str(key);
+        text.push('\n');
+    }
+
+    let new_text = interactive_edit(text)?;
+
+    let mut selected: Vec<String> = Vec::new();
+    for line in new_text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if keys.iter().any(|key| key == line) {
+            selected.push(line.to_string());
+        } else {
+            eprintln!("Ignoring unknown key {}.", line);
+        }
+    }
+
+    Ok(selected)
+}
+
 pub fn interactive_edit(text: String) -> Result<String, Box<dyn error::Error>> {
     let editor = find_editor();
 
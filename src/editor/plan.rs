@@ -0,0 +1,38 @@
+use std::io::{self, IsTerminal};
+
+use crate::aws::parameter_diff::ParameterDiff;
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Print the changes we're about to make: first a summary of which
+/// keys are created, updated and deleted, then the per-key details.
+/// SecureString values are always masked.
+pub fn print(diff: &ParameterDiff) {
+    let colour = io::stderr().is_terminal();
+
+    for line in diff.summary() {
+        eprintln!("{}", colourise(&line, colour));
+    }
+    eprintln!("\nDetails:");
+    for line in diff.render(false) {
+        eprintln!("  {}", colourise(&line, colour));
+    }
+    eprintln!();
+}
+
+// Colour a line based on its leading +, - or ~ marker.
+fn colourise(line: &str, colour: bool) -> String {
+    if !colour {
+        return line.to_string();
+    }
+
+    match line.trim_start().chars().next() {
+        Some('+') => format!("{}{}{}", GREEN, line, RESET),
+        Some('-') => format!("{}{}{}", RED, line, RESET),
+        Some('~') => format!("{}{}{}", YELLOW, line, RESET),
+        _ => line.to_string(),
+    }
+}
@@ -0,0 +1,23 @@
+use std::io::{self, IsTerminal};
+
+use text_io::read;
+
+/// Ask the user a yes/no question on the terminal.  Only an explicit
+/// "y" or "yes" counts as consent, anything else is a no.  If stdin
+/// isn't a terminal there's nobody to ask, so the answer is no.
+pub fn confirm(question: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        eprintln!("{}", question);
+        eprintln!("Standard input is not a terminal, refusing to continue.  Pass --yes to skip this check.");
+        return false;
+    }
+
+    eprint!("{} [y/N] ", question);
+    // reads until a \n is encountered
+    let answer: String = read!("{}\n");
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => true,
+        _ => false,
+    }
+}
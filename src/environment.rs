@@ -0,0 +1,2 @@
+pub mod env_formats;
+pub mod env_names;
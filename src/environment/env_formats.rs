@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::error;
+use std::str::FromStr;
+
+use crate::aws::parameter_store::ParameterError;
+
+/// The ways keez can write out environment variables.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvFormat {
+    /// NAME="value" lines, as read by dotenv libraries and
+    /// docker-compose.
+    Dotenv,
+    /// export NAME='value' lines, for bash, zsh and other POSIX shells.
+    Shell,
+    /// set -gx NAME 'value' lines, for fish.
+    Fish,
+    /// A JSON object mapping names to values.
+    Json,
+    /// NAME=value lines, as read by `docker run --env-file`.
+    Docker,
+}
+
+impl FromStr for EnvFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dotenv" => Ok(EnvFormat::Dotenv),
+            "bash" | "zsh" | "sh" => Ok(EnvFormat::Shell),
+            "fish" => Ok(EnvFormat::Fish),
+            "json" => Ok(EnvFormat::Json),
+            "docker" => Ok(EnvFormat::Docker),
+            _ => Err(format!(
+                "unknown format {:?}, expected dotenv, bash, zsh, fish, json or docker",
+                s
+            )),
+        }
+    }
+}
+
+/// Write out `variables` in the given format.
+pub fn render(
+    variables: &BTreeMap<String, String>,
+    format: EnvFormat,
+) -> Result<String, Box<dyn error::Error>> {
+    if format == EnvFormat::Json {
+        return Ok(format!("{}\n", serde_json::to_string_pretty(variables)?));
+    }
+
+    let mut output = String::new();
+    for (name, value) in variables {
+        let line = match format {
+            EnvFormat::Dotenv => format!("{}={}", name, dotenv_quote(value)),
+            EnvFormat::Shell => format!("export {}={}", name, shell_quote(value)),
+            EnvFormat::Fish => format!("set -gx {} {}", name, fish_quote(value)),
+            EnvFormat::Docker => {
+                // Docker takes everything after the = literally, up to
+                // the end of the line, so there's no way to escape a
+                // newline.
+                if value.contains('\n') || value.contains('\r') {
+                    return Err(ParameterError::Unsupported(format!(
+                        "the value of {} spans several lines, which Docker env files can't hold",
+                        name
+                    ))
+                    .into());
+                }
+                format!("{}={}", name, value)
+            }
+            EnvFormat::Json => unreachable!(),
+        };
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+// Double quotes, with backslash escapes for the characters dotenv
+// parsers would otherwise interpret.  Loaders disagree on escaping `$`
+// (python-dotenv and Docker Compose keep the backslash), so values
+// with a `$` go in single quotes, which nothing expands, as long as
+// they fit there.  Otherwise `$` and backticks are escaped too, as
+// leaving them would get them expanded.
+fn dotenv_quote(value: &str) -> String {
+    if value.contains('$') && !value.contains(['\'', '\n', '\r']) {
+        return format!("'{}'", value);
+    }
+
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '$' => quoted.push_str("\\$"),
+            '`' => quoted.push_str("\\`"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Single quotes, in which POSIX shells interpret nothing at all.  A
+// single quote is written by closing the quotes, adding an escaped
+// quote, and opening them again.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Fish single quotes only interpret \\ and \'.
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dotenv_single_quotes_values_with_dollar() {
+        assert_eq!(dotenv_quote("a$b"), "'a$b'");
+    }
+
+    #[test]
+    fn dotenv_escapes_dollar_which_cannot_be_single_quoted() {
+        assert_eq!(dotenv_quote("a'$b"), "\"a'\\$b\"");
+        assert_eq!(dotenv_quote("$x\n`y`"), "\"\\$x\\n\\`y\\`\"");
+    }
+
+    #[test]
+    fn dotenv_escapes_double_quotes() {
+        assert_eq!(dotenv_quote("it's \"x\""), "\"it's \\\"x\\\"\"");
+    }
+}
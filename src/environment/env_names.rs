@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::error;
+use std::str::FromStr;
+
+use crate::aws::parameter_store::{ParameterCollection, ParameterError};
+
+/// How to turn a parameter's name into an environment variable name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvNaming {
+    /// Use only the last path segment: /app/db/host becomes HOST.
+    LastSegment,
+    /// Use the whole path below the prefix: /app/db/host becomes
+    /// DB_HOST when reading /app.
+    RelativePath,
+}
+
+impl FromStr for EnvNaming {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "last-segment" => Ok(EnvNaming::LastSegment),
+            "relative-path" => Ok(EnvNaming::RelativePath),
+            _ => Err(format!(
+                "unknown naming {:?}, expected last-segment or relative-path",
+                s
+            )),
+        }
+    }
+}
+
+/// Turn a key, relative to its prefix, into an environment variable
+/// name.  Letters are uppercased, and anything which isn't a letter,
+/// digit or underscore becomes an underscore.
+pub fn env_name(relative_key: &str, naming: EnvNaming) -> String {
+    let source = match naming {
+        EnvNaming::LastSegment => relative_key.rsplit('/').next().unwrap_or(relative_key),
+        EnvNaming::RelativePath => relative_key,
+    };
+
+    let mut name: String = source
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    // Variable names can't start with a digit.
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+// environment_from maps the parameters in each collection to
+// environment variables.  When the same variable comes out of several
+// collections, the one listed last wins.  Two keys in the same
+// collection mapping to one variable is an error, since neither has a
+// better claim to it.
+pub fn environment_from(
+    collections: &[ParameterCollection],
+    naming: EnvNaming,
+) -> Result<BTreeMap<String, String>, Box<dyn error::Error>> {
+    let mut environment: BTreeMap<String, String> = BTreeMap::new();
+    let mut origins: BTreeMap<String, String> = BTreeMap::new();
+
+    for collection in collections {
+        let hierarchy = format!("{}/", collection.prefix().trim_end_matches('/'));
+
+        let mut keys: Vec<&String> = collection.parameters().keys().collect();
+        keys.sort();
+
+        let mut seen: BTreeMap<String, &String> = BTreeMap::new();
+        for key in keys {
+            let relative = key.strip_prefix(&hierarchy).unwrap_or(key);
+            let name = env_name(relative, naming);
+
+            if let Some(other) = seen.insert(name.clone(), key) {
+                return Err(ParameterError::EnvVarCollision(format!(
+                    "{} and {} both map to {}",
+                    other, key, name
+                ))
+                .into());
+            }
+
+            if let Some(previous) = origins.insert(name.clone(), key.clone()) {
+                eprintln!("{} from {} overrides {}.", name, key, previous);
+            }
+            environment.insert(name, collection.parameters()[key].value().clone());
+        }
+    }
+
+    Ok(environment)
+}
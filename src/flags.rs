@@ -1 +1,2 @@
+pub mod list_format;
 pub mod operation_mode;
@@ -0,0 +1,25 @@
+use std::str::FromStr;
+
+/// How `keez ls` presents the parameters it found.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListFormat {
+    Tree,
+    Flat,
+    Json,
+}
+
+impl FromStr for ListFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(ListFormat::Tree),
+            "flat" => Ok(ListFormat::Flat),
+            "json" => Ok(ListFormat::Json),
+            _ => Err(format!(
+                "unknown output format {:?}, expected tree, flat or json",
+                s
+            )),
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use structopt::StructOpt;
 
 mod aws;
+mod backend;
 mod cli;
 mod editor;
+mod environment;
 mod flags;
 mod secrets;
 
@@ -17,28 +19,107 @@ fn main() {
             export_filename,
             insecure_output,
             source,
+            label,
+            format,
+            env_names,
+            passphrase,
+            recipients,
+            key_name,
         } => {
             cli::cmd_export::run(
                 args.clone(),
                 export_filename.clone(),
                 insecure_output.clone(),
                 source.clone(),
+                label.clone(),
+                format.clone(),
+                env_names.clone(),
+                passphrase.clone(),
+                recipients.clone(),
+                key_name.clone(),
                 args.operation_mode().clone(),
             );
         }
+        cli::KeezCommand::Get {
+            name,
+            version,
+            label,
+            json,
+        } => {
+            cli::cmd_get::run(
+                args.clone(),
+                name.clone(),
+                version.clone(),
+                label.clone(),
+                json.clone(),
+            );
+        }
+        cli::KeezCommand::History {
+            name,
+            recursive,
+            reveal,
+        } => {
+            cli::cmd_history::run(
+                args.clone(),
+                name.clone(),
+                recursive.clone(),
+                reveal.clone(),
+            );
+        }
         cli::KeezCommand::Import {
             import_filename,
             destination,
             edit,
+            input_format,
+            parameter_type,
+            passphrase,
+            key_name,
         } => {
             cli::cmd_import::run(
                 args.clone(),
                 import_filename.clone(),
                 destination.clone(),
                 edit.clone(),
+                input_format.clone(),
+                parameter_type.clone(),
+                passphrase.clone(),
+                key_name.clone(),
                 args.operation_mode().clone(),
             );
         }
+        cli::KeezCommand::Diff {
+            from,
+            to,
+            from_file,
+            to_file,
+            reveal,
+            passphrase,
+        } => {
+            cli::cmd_diff::run(
+                args.clone(),
+                from.clone(),
+                from_file.clone(),
+                to.clone(),
+                to_file.clone(),
+                reveal.clone(),
+                passphrase.clone(),
+            );
+        }
+        cli::KeezCommand::Exec {
+            prefixes,
+            env_names,
+            command,
+        } => {
+            cli::cmd_exec::run(
+                args.clone(),
+                prefixes.clone(),
+                env_names.clone(),
+                command.clone(),
+            );
+        }
+        cli::KeezCommand::Expiring { prefix, days } => {
+            cli::cmd_expiring::run(args.clone(), prefix.clone(), days.clone());
+        }
         cli::KeezCommand::Edit { prefix } => {
             cli::cmd_edit::run(args.clone(), prefix.clone(), args.operation_mode().clone());
         }
@@ -46,17 +127,146 @@ fn main() {
             source,
             destination,
             edit,
+            source_region,
+            source_profile,
+            destination_region,
+            destination_profile,
         } => {
             cli::cmd_copy::run(
                 args.clone(),
                 source.clone(),
                 destination.clone(),
                 edit.clone(),
+                args.ssm_connection()
+                    .with_overrides(source_region, source_profile),
+                args.ssm_connection()
+                    .with_overrides(destination_region, destination_profile),
                 args.operation_mode().clone(),
             );
         }
-        cli::KeezCommand::Create {} => {
-            cli::cmd_create::run(args.clone(), args.operation_mode().clone());
+        cli::KeezCommand::Create {
+            from_file,
+            input_format,
+            prefix,
+            parameter_type,
+        } => {
+            cli::cmd_create::run(
+                args.clone(),
+                from_file.clone(),
+                input_format.clone(),
+                prefix.clone(),
+                parameter_type.clone(),
+                args.operation_mode().clone(),
+            );
+        }
+        cli::KeezCommand::Inspect { file } => {
+            cli::cmd_inspect::run(args.clone(), file.clone());
+        }
+        cli::KeezCommand::Key { key_name, command } => {
+            cli::cmd_key::run(
+                args.clone(),
+                key_name.clone(),
+                command.clone(),
+                args.operation_mode().clone(),
+            );
+        }
+        cli::KeezCommand::Keygen { force } => {
+            cli::cmd_keygen::run(args.clone(), force.clone());
+        }
+        cli::KeezCommand::Label {
+            name,
+            labels,
+            recursive,
+            version,
+            remove,
+        } => {
+            cli::cmd_label::run(
+                args.clone(),
+                name.clone(),
+                labels.clone(),
+                recursive.clone(),
+                version.clone(),
+                remove.clone(),
+                args.operation_mode().clone(),
+            );
+        }
+        cli::KeezCommand::Ls {
+            prefix,
+            non_recursive,
+            depth,
+            output,
+        } => {
+            cli::cmd_ls::run(
+                args.clone(),
+                prefix.clone(),
+                non_recursive.clone(),
+                depth.clone(),
+                output.clone(),
+            );
+        }
+        cli::KeezCommand::Move {
+            source,
+            destination,
+        } => {
+            cli::cmd_move::run(
+                args.clone(),
+                source.clone(),
+                destination.clone(),
+                args.operation_mode().clone(),
+            );
+        }
+        cli::KeezCommand::Rollback {
+            name,
+            recursive,
+            to_version,
+            to_time,
+        } => {
+            cli::cmd_rollback::run(
+                args.clone(),
+                name.clone(),
+                recursive.clone(),
+                to_version.clone(),
+                to_time.clone(),
+                args.operation_mode().clone(),
+            );
+        }
+        cli::KeezCommand::Set {
+            name,
+            value,
+            from_file,
+            parameter_type,
+            overwrite,
+        } => {
+            cli::cmd_set::run(
+                args.clone(),
+                name.clone(),
+                value.clone(),
+                from_file.clone(),
+                parameter_type.clone(),
+                overwrite.clone(),
+                args.operation_mode().clone(),
+            );
+        }
+        cli::KeezCommand::Sync {
+            source,
+            destination,
+            delete,
+        } => {
+            cli::cmd_sync::run(
+                args.clone(),
+                source.clone(),
+                destination.clone(),
+                delete.clone(),
+                args.operation_mode().clone(),
+            );
+        }
+        cli::KeezCommand::Delete { prefix, edit } => {
+            cli::cmd_delete::run(
+                args.clone(),
+                prefix.clone(),
+                edit.clone(),
+                args.operation_mode().clone(),
+            );
         }
     }
 }
@@ -1,2 +1,5 @@
+pub mod export_file;
 pub mod keychain_access;
+pub mod passphrase;
+pub mod recipients;
 pub mod symmetric_store;
@@ -0,0 +1,330 @@
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::aws::parameter_store::ParameterCollection;
+use crate::backend::parameter_backend::StoreLocation;
+use crate::secrets;
+use crate::secrets::keychain_access::KeyName;
+use crate::secrets::recipients::Recipient;
+
+// Export files start with this line, then a line of JSON describing
+// the export (see ExportHeader), and then the encrypted parameters.
+// Files written before the header was introduced are nothing but the
+// encrypted parameters.
+const MAGIC: &str = "keez-export\n";
+
+// Bump this whenever the header or payload change in a way older
+// versions of keez wouldn't understand.
+const FORMAT_VERSION: u32 = 1;
+
+/// How the parameters in an export file are encrypted.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EncryptionScheme {
+    /// With the key in the exporting user's system keychain.
+    Keychain,
+    /// With a key derived from a passphrase.
+    Passphrase,
+    /// To the public keys of one or more recipients.
+    Recipients,
+}
+
+impl fmt::Display for EncryptionScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                EncryptionScheme::Keychain => "keychain",
+                EncryptionScheme::Passphrase => "passphrase",
+                EncryptionScheme::Recipients => "recipients",
+            }
+        )
+    }
+}
+
+/// The unencrypted description at the start of an export file.  It
+/// says how to decrypt the file, and where its parameters came from,
+/// but never contains any values.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportHeader {
+    pub format_version: u32,
+    pub encryption: EncryptionScheme,
+    /// Identifies the key, for the keychain scheme: its fingerprint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    /// Which keychain key to decrypt with, for the keychain scheme.
+    /// Files without one were made with the default key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_name: Option<String>,
+    /// The public keys the file was encrypted to, for the recipients
+    /// scheme.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+    /// When the export was made, in RFC 3339 format.
+    pub created: String,
+    pub source_prefix: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_account: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_region: Option<String>,
+    pub parameter_count: usize,
+}
+
+/// What to encrypt an export with.
+pub enum Encryption {
+    Keychain(KeyName),
+    Passphrase(String),
+    Recipients(Vec<Recipient>),
+}
+
+/// Encrypt `parameters`, and put a header in front describing them,
+/// ready to be written to an export file.
+pub fn encode(
+    parameters: &ParameterCollection,
+    encryption: &Encryption,
+    location: &StoreLocation,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let yaml_blob = serde_yaml::to_string(parameters)?;
+
+    let (scheme, key_id, key_name, recipients, payload) = match encryption {
+        Encryption::Keychain(name) => {
+            let key = secrets::keychain_access::get_symmetric_key(name)?;
+            (
+                EncryptionScheme::Keychain,
+                Some(secrets::symmetric_store::fingerprint(&key)),
+                Some(name.to_string()),
+                Vec::new(),
+                secrets::symmetric_store::encrypt_with_passphrase(yaml_blob, &key)?,
+            )
+        }
+        Encryption::Passphrase(passphrase) => (
+            EncryptionScheme::Passphrase,
+            None,
+            None,
+            Vec::new(),
+            secrets::symmetric_store::encrypt_with_passphrase(yaml_blob, passphrase)?,
+        ),
+        Encryption::Recipients(recipients) => (
+            EncryptionScheme::Recipients,
+            None,
+            None,
+            recipients.iter().map(|r| r.to_string()).collect(),
+            secrets::recipients::encrypt(yaml_blob.as_bytes(), recipients)?,
+        ),
+    };
+
+    let header = ExportHeader {
+        format_version: FORMAT_VERSION,
+        encryption: scheme,
+        key_id,
+        key_name,
+        recipients,
+        created: Utc::now().to_rfc3339(),
+        source_prefix: parameters.prefix().clone(),
+        source_account: location.account.clone(),
+        source_region: location.region.clone(),
+        parameter_count: parameters.parameters().len(),
+    };
+
+    assemble(&header, payload)
+}
+
+/// Re-encrypt an export made with the keychain key `old_key`, so that
+/// it can be read with `new_key`, named `new_key_name`, instead.  The
+/// header is kept, apart from the key fingerprint and name; files from
+/// before headers were introduced get one, though their source account
+/// and region are unknown.
+pub fn reencrypt(
+    data: &[u8],
+    old_key: &str,
+    new_key: &str,
+    new_key_name: &KeyName,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let (header, payload) = split(data)?;
+
+    if let Some(header) = &header {
+        if header.encryption != EncryptionScheme::Keychain {
+            return Err(format!(
+                "only keychain-encrypted exports can be re-encrypted, this one uses {}",
+                header.encryption
+            )
+            .into());
+        }
+    }
+
+    let old_id = secrets::symmetric_store::fingerprint(old_key);
+    if let Some(key_id) = header.as_ref().and_then(|header| header.key_id.as_ref()) {
+        if *key_id != old_id {
+            return Err(format!(
+                "encrypted with keychain key {}, not the old key {}",
+                key_id, old_id
+            )
+            .into());
+        }
+    }
+
+    let raw_yaml = secrets::symmetric_store::decrypt_with_passphrase(payload.to_vec(), old_key)
+        .map_err(|_| "couldn't decrypt with the old key")?;
+
+    let mut header = match header {
+        Some(header) => header,
+        None => {
+            let parameters: ParameterCollection = serde_yaml::from_str(&raw_yaml)?;
+            ExportHeader {
+                format_version: FORMAT_VERSION,
+                encryption: EncryptionScheme::Keychain,
+                key_id: None,
+                key_name: None,
+                recipients: Vec::new(),
+                created: Utc::now().to_rfc3339(),
+                source_prefix: parameters.prefix().clone(),
+                source_account: None,
+                source_region: None,
+                parameter_count: parameters.parameters().len(),
+            }
+        }
+    };
+    header.key_id = Some(secrets::symmetric_store::fingerprint(new_key));
+    header.key_name = Some(new_key_name.to_string());
+
+    let payload = secrets::symmetric_store::encrypt_with_passphrase(raw_yaml, new_key)?;
+    assemble(&header, payload)
+}
+
+// Put the magic line and the header in front of the encrypted
+// parameters.
+fn assemble(header: &ExportHeader, payload: Vec<u8>) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let mut output = MAGIC.as_bytes().to_vec();
+    output.extend(serde_json::to_string(header)?.into_bytes());
+    output.push(b'\n');
+    output.extend(payload);
+    Ok(output)
+}
+
+/// Read just the header of an export file, without decrypting
+/// anything.  Files from before headers were introduced have none.
+pub fn read_header(path: &Path) -> Result<Option<ExportHeader>, Box<dyn error::Error>> {
+    let data = fs::read(path)?;
+    Ok(split(&data)?.0)
+}
+
+// Split an export file into its header, if it has one, and the
+// encrypted parameters.
+fn split(data: &[u8]) -> Result<(Option<ExportHeader>, &[u8]), Box<dyn error::Error>> {
+    let rest = match data.strip_prefix(MAGIC.as_bytes()) {
+        Some(rest) => rest,
+        None => return Ok((None, data)),
+    };
+
+    let end = rest
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or("malformed export file header")?;
+    let header: ExportHeader = serde_json::from_slice(&rest[..end])?;
+
+    if header.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "this export uses format version {}, but this keez only understands up to {}; please upgrade",
+            header.format_version, FORMAT_VERSION
+        )
+        .into());
+    }
+
+    Ok((Some(header), &rest[end + 1..]))
+}
+
+/// Read and decrypt a file written by `keez export`.  The header says
+/// how: with the local identity, the given passphrase, or the key in
+/// the system keychain.  Files without a header are decrypted with the
+/// local identity if they were encrypted to recipients, and otherwise
+/// with the passphrase if one is given, or else the keychain key.
+/// Unless `key_name` is given, the keychain key named in the header is
+/// used, or the default key if there's none.
+pub fn read(
+    path: &Path,
+    passphrase: Option<&str>,
+    key_name: Option<&KeyName>,
+    debug: bool,
+) -> Result<ParameterCollection, Box<dyn error::Error>> {
+    let data: Vec<u8> = fs::read(path)?;
+    let (header, payload) = split(&data)?;
+
+    if debug {
+        eprintln!("Export file header:");
+        eprintln!("{:?}", header);
+    }
+
+    let scheme = match &header {
+        Some(header) => header.encryption,
+        None if passphrase.is_some() => EncryptionScheme::Passphrase,
+        None => EncryptionScheme::Keychain,
+    };
+
+    let raw_yaml = match scheme {
+        EncryptionScheme::Recipients => {
+            let identity = secrets::recipients::local_identity()?.ok_or_else(|| {
+                format!(
+                    "{} is encrypted to recipients, but you have no identity yet (see `keez keygen`)",
+                    path.display()
+                )
+            })?;
+            String::from_utf8(secrets::recipients::decrypt(payload, &identity)?)?
+        }
+        EncryptionScheme::Passphrase => {
+            let passphrase = passphrase.ok_or_else(|| {
+                format!(
+                    "{} is encrypted with a passphrase, please pass --passphrase",
+                    path.display()
+                )
+            })?;
+            secrets::symmetric_store::decrypt_with_passphrase(payload.to_vec(), passphrase)?
+        }
+        EncryptionScheme::Keychain => {
+            let name = match (key_name, header.as_ref().and_then(|h| h.key_name.as_ref())) {
+                (Some(name), _) => name.clone(),
+                (None, Some(recorded)) => recorded.parse()?,
+                (None, None) => KeyName::default(),
+            };
+            let key = match secrets::keychain_access::find_symmetric_key(&name)? {
+                Some(key) => key,
+                None => {
+                    return Err(format!(
+                        "{} was encrypted with keychain key {:?}, which you don't have (see `keez key import`)",
+                        path.display(),
+                        name.to_string()
+                    )
+                    .into())
+                }
+            };
+            let actual = secrets::symmetric_store::fingerprint(&key);
+
+            if let Some(expected) = header.as_ref().and_then(|header| header.key_id.as_ref()) {
+                if *expected != actual {
+                    return Err(format!(
+                        "{} was encrypted with keychain key {}, but your key {:?} is {}",
+                        path.display(),
+                        expected,
+                        name.to_string(),
+                        actual
+                    )
+                    .into());
+                }
+            }
+            secrets::symmetric_store::decrypt_with_passphrase(payload.to_vec(), &key)?
+        }
+    };
+
+    if debug {
+        eprintln!("Read YAML from encrypted file:");
+        eprintln!("{}", raw_yaml);
+    }
+
+    // Deserialize it back to a Rust type.
+    Ok(serde_yaml::from_str(&raw_yaml)?)
+}
@@ -1,16 +1,81 @@
+use std::fmt;
+use std::str::FromStr;
+
 use keyring::Keyring;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
 const APP_NAME: &str = "keez";
+// The default key is stored under the entries from before keys had
+// names, so existing exports keep working.
 const SYMMETRIC_KEY_ID: &str = "temporary symmetric key";
+const IDENTITY_KEY_ID: &str = "x25519 identity";
+
+const DEFAULT_KEY_NAME: &str = "default";
+
+/// The name of a keychain key for exports, so that e.g. each project
+/// or environment can have its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyName(String);
+
+impl KeyName {
+    fn entry_id(&self) -> String {
+        if self.0 == DEFAULT_KEY_NAME {
+            SYMMETRIC_KEY_ID.to_string()
+        } else {
+            format!("symmetric key {}", self.0)
+        }
+    }
+
+    // Retired keys are stored one per entry, by fingerprint, with a
+    // list of those fingerprints in one more entry, since keychains
+    // can't be searched.
+    fn retired_entry_id(&self, fingerprint: &str) -> String {
+        format!("retired symmetric key {} {}", self.0, fingerprint)
+    }
+
+    fn retired_index_id(&self) -> String {
+        format!("retired symmetric keys {}", self.0)
+    }
+}
+
+impl Default for KeyName {
+    fn default() -> Self {
+        KeyName(DEFAULT_KEY_NAME.to_string())
+    }
+}
+
+impl fmt::Display for KeyName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for KeyName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty()
+            || !s
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        {
+            return Err(format!(
+                "invalid key name {:?}, use letters, digits, '-', '_' and '.'",
+                s
+            ));
+        }
+        Ok(KeyName(s.to_string()))
+    }
+}
 
 /// This function will return the stored key intended for symmetric
 /// encryption of exported parameter values, and if it doesn't yet
 /// exist (e.g., on first invocation) will generate a key and store
 /// it, too.
-pub fn get_symmetric_key() -> Result<String, keyring::KeyringError> {
-    let keyring = Keyring::new(APP_NAME, SYMMETRIC_KEY_ID);
+pub fn get_symmetric_key(name: &KeyName) -> Result<String, keyring::KeyringError> {
+    let entry_id = name.entry_id();
+    let keyring = Keyring::new(APP_NAME, &entry_id);
     let get_password_result = keyring.get_password();
 
     // Only if the error was "not found" do we know how to recover.
@@ -18,14 +83,15 @@ pub fn get_symmetric_key() -> Result<String, keyring::KeyringError> {
     match get_password_result {
         Ok(item) => return Ok(item),
         Err(keyring::KeyringError::NoPasswordFound) => {
-            return set_symmetric_key();
+            return set_symmetric_key(name);
         }
         Err(other) => return Err(other),
     }
 }
 
-pub fn set_symmetric_key() -> Result<String, keyring::KeyringError> {
-    let keyring = Keyring::new(APP_NAME, SYMMETRIC_KEY_ID);
+pub fn set_symmetric_key(name: &KeyName) -> Result<String, keyring::KeyringError> {
+    let entry_id = name.entry_id();
+    let keyring = Keyring::new(APP_NAME, &entry_id);
 
     let rand_string: String = thread_rng().sample_iter(&Alphanumeric).take(128).collect();
 
@@ -36,3 +102,84 @@ pub fn set_symmetric_key() -> Result<String, keyring::KeyringError> {
         Err(whatever) => return Err(whatever),
     }
 }
+
+/// Look up the export key, without generating one if there is none.
+pub fn find_symmetric_key(name: &KeyName) -> Result<Option<String>, keyring::KeyringError> {
+    find(&name.entry_id())
+}
+
+/// Replace the export key with the given one, e.g. a key imported from
+/// a teammate.
+pub fn store_symmetric_key(name: &KeyName, key: &str) -> Result<(), keyring::KeyringError> {
+    Keyring::new(APP_NAME, &name.entry_id()).set_password(key)
+}
+
+/// Keep a key which `keez key rotate` or `keez key import` replaces,
+/// so that exports made with it can still be re-encrypted.  Every
+/// retired key is kept, not just the last one.
+pub fn retire_symmetric_key(
+    name: &KeyName,
+    fingerprint: &str,
+    key: &str,
+) -> Result<(), keyring::KeyringError> {
+    let entry_id = name.retired_entry_id(fingerprint);
+    Keyring::new(APP_NAME, &entry_id).set_password(key)?;
+
+    let index_id = name.retired_index_id();
+    let mut fingerprints = retired_fingerprints(name)?;
+    if !fingerprints.iter().any(|f| f == fingerprint) {
+        fingerprints.push(fingerprint.to_string());
+        Keyring::new(APP_NAME, &index_id).set_password(&fingerprints.join(","))?;
+    }
+    Ok(())
+}
+
+/// All keys retired under `name`, oldest first, with their
+/// fingerprints.
+pub fn find_retired_symmetric_keys(
+    name: &KeyName,
+) -> Result<Vec<(String, String)>, keyring::KeyringError> {
+    let mut keys = Vec::new();
+    for fingerprint in retired_fingerprints(name)? {
+        if let Some(key) = find(&name.retired_entry_id(&fingerprint))? {
+            keys.push((fingerprint, key));
+        }
+    }
+    Ok(keys)
+}
+
+fn retired_fingerprints(name: &KeyName) -> Result<Vec<String>, keyring::KeyringError> {
+    Ok(find(&name.retired_index_id())?
+        .map(|index| {
+            index
+                .split(',')
+                .filter(|f| !f.is_empty())
+                .map(|f| f.to_string())
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Return the local identity for recipient-encrypted exports, as
+/// written by `keez keygen`, or `None` if there isn't one yet.
+pub fn get_identity() -> Result<Option<String>, keyring::KeyringError> {
+    find(IDENTITY_KEY_ID)
+}
+
+pub fn set_identity(identity: &str) -> Result<(), keyring::KeyringError> {
+    let keyring = Keyring::new(APP_NAME, IDENTITY_KEY_ID);
+
+    keyring.set_password(identity)
+}
+
+// Look up a keychain entry, treating a missing one as `None` rather
+// than an error.
+fn find(key_id: &str) -> Result<Option<String>, keyring::KeyringError> {
+    let keyring = Keyring::new(APP_NAME, key_id);
+
+    match keyring.get_password() {
+        Ok(item) => return Ok(Some(item)),
+        Err(keyring::KeyringError::NoPasswordFound) => return Ok(None),
+        Err(other) => return Err(other),
+    }
+}
@@ -0,0 +1,96 @@
+use std::env;
+use std::error;
+use std::str::FromStr;
+
+/// Where to get a passphrase for encrypting or decrypting an export.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PassphraseSource {
+    /// Ask on the terminal.
+    Prompt,
+    /// Read it from the named environment variable.
+    Env(String),
+    /// Read the first line from an open file descriptor.
+    Fd(i32),
+}
+
+impl FromStr for PassphraseSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "prompt" {
+            return Ok(PassphraseSource::Prompt);
+        }
+        if let Some(name) = s.strip_prefix("env:") {
+            return Ok(PassphraseSource::Env(name.to_string()));
+        }
+        if let Some(fd) = s.strip_prefix("fd:") {
+            // Standard input, output and error are keez's own.
+            if let Ok(fd) = fd.parse() {
+                if fd > 2 {
+                    return Ok(PassphraseSource::Fd(fd));
+                }
+                return Err(format!(
+                    "invalid passphrase source {:?}, use a file descriptor above 2",
+                    s
+                ));
+            }
+        }
+
+        Err(format!(
+            "invalid passphrase source {:?}, expected prompt, env:NAME or fd:N",
+            s
+        ))
+    }
+}
+
+/// Get the passphrase from `source`.  When prompting for a passphrase
+/// to encrypt with, set `confirm` to have it typed twice, since a typo
+/// would make the export impossible to decrypt.
+pub fn read(source: &PassphraseSource, confirm: bool) -> Result<String, Box<dyn error::Error>> {
+    let passphrase = match source {
+        PassphraseSource::Prompt => {
+            let passphrase = rpassword::read_password_from_tty(Some("Passphrase: "))?;
+            if confirm {
+                let again = rpassword::read_password_from_tty(Some("Passphrase (again): "))?;
+                if again != passphrase {
+                    return Err("the passphrases didn't match".into());
+                }
+            }
+            passphrase
+        }
+        PassphraseSource::Env(name) => {
+            env::var(name).map_err(|_| format!("environment variable {} is not set", name))?
+        }
+        PassphraseSource::Fd(fd) => read_fd(*fd)?,
+    };
+
+    if passphrase.is_empty() {
+        return Err("the passphrase is empty".into());
+    }
+    Ok(passphrase)
+}
+
+#[cfg(unix)]
+fn read_fd(fd: i32) -> Result<String, Box<dyn error::Error>> {
+    use std::fs::File;
+    use std::io::Read;
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::FromRawFd;
+
+    // The descriptor isn't ours to close, so never drop the File.
+    let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+
+    // Read a byte at a time, so nothing past the first line is taken
+    // from the descriptor.
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    while file.read(&mut byte)? == 1 && byte[0] != b'\n' {
+        line.push(byte[0]);
+    }
+    Ok(String::from_utf8(line)?.trim_end_matches('\r').to_string())
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32) -> Result<String, Box<dyn error::Error>> {
+    Err("reading a passphrase from a file descriptor is only supported on Unix".into())
+}
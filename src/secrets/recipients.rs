@@ -0,0 +1,348 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::secrets;
+
+// Files encrypted to recipients start with this line, followed by one
+// line per recipient and a closing "---" line, much like age does.
+// After that comes the ciphertext.
+const MAGIC: &str = "keez-recipients/v1\n";
+const STANZA_PREFIX: &str = "-> X25519 ";
+const HEADER_END: &str = "---\n";
+
+// How public keys are written, so they're recognisable when pasted
+// around.
+const RECIPIENT_PREFIX: &str = "keez-x25519:";
+
+// Mixed into the key derivation, so the wrapping keys can't be
+// confused with keys derived for anything else.
+const WRAP_INFO: &[u8] = b"keez-recipients/v1 X25519 file key";
+
+const KEY_LEN: usize = 32;
+
+/// A local X25519 key pair, which can decrypt files encrypted to its
+/// `Recipient`.
+pub struct Identity {
+    secret: StaticSecret,
+}
+
+/// Somebody's public key, to encrypt files to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipient {
+    public: [u8; KEY_LEN],
+}
+
+impl Identity {
+    pub fn generate() -> Result<Identity, Box<dyn error::Error>> {
+        Ok(Identity {
+            secret: StaticSecret::from(random_key()?),
+        })
+    }
+
+    /// Read an identity written by `encoded`.
+    pub fn from_encoded(encoded: &str) -> Result<Identity, Box<dyn error::Error>> {
+        Ok(Identity {
+            secret: StaticSecret::from(decode_key(encoded)?),
+        })
+    }
+
+    /// The secret key as text, for storing in the keychain.
+    pub fn encoded(&self) -> String {
+        base64::encode_config(self.secret.to_bytes(), base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn recipient(&self) -> Recipient {
+        Recipient {
+            public: *PublicKey::from(&self.secret).as_bytes(),
+        }
+    }
+}
+
+/// The identity stored in the system keychain by `keez keygen`, if
+/// there is one.
+pub fn local_identity() -> Result<Option<Identity>, Box<dyn error::Error>> {
+    match secrets::keychain_access::get_identity()? {
+        Some(encoded) => Ok(Some(Identity::from_encoded(&encoded)?)),
+        None => Ok(None),
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            RECIPIENT_PREFIX,
+            base64::encode_config(self.public, base64::URL_SAFE_NO_PAD)
+        )
+    }
+}
+
+impl FromStr for Recipient {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid recipient {:?}, expected {}...",
+                s, RECIPIENT_PREFIX
+            )
+        };
+
+        let encoded = s.strip_prefix(RECIPIENT_PREFIX).ok_or_else(invalid)?;
+        let public = decode_key(encoded).map_err(|_| invalid())?;
+        Ok(Recipient { public })
+    }
+}
+
+// Whether `data` was written by `encrypt`, as opposed to being
+// encrypted with the keychain key or a passphrase.
+fn is_recipient_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC.as_bytes())
+}
+
+/// Encrypt `plaintext` so that any one of `recipients` can decrypt
+/// it.  A random file key encrypts the data, and a copy of the file
+/// key is wrapped for each recipient using a key agreed between a
+/// fresh ephemeral key pair and the recipient's public key.
+pub fn encrypt(
+    plaintext: &[u8],
+    recipients: &[Recipient],
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    if recipients.is_empty() {
+        return Err("at least one recipient is needed".into());
+    }
+
+    let file_key = random_key()?;
+
+    let mut header = String::from(MAGIC);
+    for recipient in recipients {
+        let ephemeral = StaticSecret::from(random_key()?);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let shared = agree(&ephemeral, &recipient.public)
+            .map_err(|_| format!("{} is not a usable public key", recipient))?;
+
+        let wrapping_key =
+            derive_wrapping_key(&shared, ephemeral_public.as_bytes(), &recipient.public)?;
+        let wrapped = seal(&wrapping_key, &[], &file_key)?;
+
+        header.push_str(&format!(
+            "{}{} {}\n",
+            STANZA_PREFIX,
+            base64::encode_config(ephemeral_public.as_bytes(), base64::URL_SAFE_NO_PAD),
+            base64::encode_config(&wrapped, base64::URL_SAFE_NO_PAD)
+        ));
+    }
+    header.push_str(HEADER_END);
+
+    // The header is authenticated along with the data, so the
+    // recipient list can't be tampered with.
+    let mut output = header.clone().into_bytes();
+    output.extend(seal(&file_key, header.as_bytes(), plaintext)?);
+    Ok(output)
+}
+
+/// Decrypt data written by `encrypt`, if `identity` is one of its
+/// recipients.
+pub fn decrypt(data: &[u8], identity: &Identity) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let malformed = || -> Box<dyn error::Error> { "malformed recipient header".into() };
+
+    if !is_recipient_encrypted(data) {
+        return Err(malformed());
+    }
+
+    // Find the end of the header: the first "---" line.
+    let mut header_len = None;
+    let mut offset = 0;
+    for line in data.split_inclusive(|b| *b == b'\n') {
+        offset += line.len();
+        if line == HEADER_END.as_bytes() {
+            header_len = Some(offset);
+            break;
+        }
+    }
+    let header_len = header_len.ok_or_else(malformed)?;
+    let (header, ciphertext) = data.split_at(header_len);
+    let header_text = std::str::from_utf8(header).map_err(|_| malformed())?;
+
+    let own_public = identity.recipient().public;
+    for line in header_text.lines() {
+        let stanza = match line.strip_prefix(STANZA_PREFIX) {
+            Some(stanza) => stanza,
+            None => continue,
+        };
+
+        let mut fields = stanza.split(' ');
+        let ephemeral_public = decode_key(fields.next().ok_or_else(malformed)?)?;
+        let wrapped = base64::decode_config(
+            fields.next().ok_or_else(malformed)?,
+            base64::URL_SAFE_NO_PAD,
+        )?;
+
+        let shared = agree(&identity.secret, &ephemeral_public)?;
+        let wrapping_key = derive_wrapping_key(&shared, &ephemeral_public, &own_public)?;
+
+        // A stanza for somebody else simply won't open.
+        if let Ok(file_key) = open(&wrapping_key, &[], &wrapped) {
+            return open(&file_key, header, ciphertext);
+        }
+    }
+
+    Err("this file wasn't encrypted to your identity".into())
+}
+
+// Agree on a shared secret.  A low-order public key makes it all
+// zeros, whatever our secret, so anybody could derive the wrapping key;
+// refuse those, as age does.
+fn agree(
+    secret: &StaticSecret,
+    public: &[u8; KEY_LEN],
+) -> Result<[u8; KEY_LEN], Box<dyn error::Error>> {
+    let shared = secret.diffie_hellman(&PublicKey::from(*public));
+    if shared.as_bytes().iter().fold(0, |acc, byte| acc | byte) == 0 {
+        return Err("low-order X25519 public key".into());
+    }
+    Ok(*shared.as_bytes())
+}
+
+fn derive_wrapping_key(
+    shared: &[u8; KEY_LEN],
+    ephemeral_public: &[u8; KEY_LEN],
+    recipient_public: &[u8; KEY_LEN],
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let mut salt = ephemeral_public.to_vec();
+    salt.extend_from_slice(recipient_public);
+
+    let mut key = vec![0u8; KEY_LEN];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, &salt)
+        .extract(shared)
+        .expand(&[WRAP_INFO], hkdf::HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut key))
+        .map_err(|_| "key derivation failed")?;
+    Ok(key)
+}
+
+// Every key seals exactly one message, so a fixed nonce is safe.
+fn seal(key: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let mut in_out = plaintext.to_vec();
+    aead_key(key)?
+        .seal_in_place_append_tag(zero_nonce(), Aad::from(aad), &mut in_out)
+        .map_err(|_| "encryption failed")?;
+    Ok(in_out)
+}
+
+fn open(key: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let mut in_out = ciphertext.to_vec();
+    let plaintext_len = aead_key(key)?
+        .open_in_place(zero_nonce(), Aad::from(aad), &mut in_out)
+        .map_err(|_| "decryption failed")?
+        .len();
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+fn aead_key(key: &[u8]) -> Result<LessSafeKey, Box<dyn error::Error>> {
+    let unbound = UnboundKey::new(&CHACHA20_POLY1305, key).map_err(|_| "invalid key length")?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+fn zero_nonce() -> Nonce {
+    Nonce::assume_unique_for_key([0u8; aead::NONCE_LEN])
+}
+
+fn random_key() -> Result<[u8; KEY_LEN], Box<dyn error::Error>> {
+    let mut key = [0u8; KEY_LEN];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| "couldn't generate a random key")?;
+    Ok(key)
+}
+
+fn decode_key(encoded: &str) -> Result<[u8; KEY_LEN], Box<dyn error::Error>> {
+    let bytes = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)?;
+    if bytes.len() != KEY_LEN {
+        return Err("a key must be 32 bytes long".into());
+    }
+
+    let mut key = [0u8; KEY_LEN];
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &[u8] = b"prefix: /app\nparameters: {}\n";
+
+    #[test]
+    fn each_recipient_can_decrypt() {
+        let alice = Identity::generate().unwrap();
+        let bob = Identity::generate().unwrap();
+
+        let data = encrypt(PLAINTEXT, &[alice.recipient(), bob.recipient()]).unwrap();
+
+        assert_eq!(decrypt(&data, &alice).unwrap(), PLAINTEXT);
+        assert_eq!(decrypt(&data, &bob).unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn others_cannot_decrypt() {
+        let alice = Identity::generate().unwrap();
+        let mallory = Identity::generate().unwrap();
+
+        let data = encrypt(PLAINTEXT, &[alice.recipient()]).unwrap();
+
+        assert!(decrypt(&data, &mallory).is_err());
+    }
+
+    // Split encrypted data into its header, as text, and the rest.
+    fn split_header(data: &[u8]) -> (String, &[u8]) {
+        let end = data
+            .windows(HEADER_END.len())
+            .position(|window| window == HEADER_END.as_bytes())
+            .unwrap()
+            + HEADER_END.len();
+        (
+            String::from_utf8(data[..end].to_vec()).unwrap(),
+            &data[end..],
+        )
+    }
+
+    #[test]
+    fn modified_header_is_rejected() {
+        let alice = Identity::generate().unwrap();
+        let bob = Identity::generate().unwrap();
+        let mallory = Identity::generate().unwrap();
+        let data = encrypt(PLAINTEXT, &[alice.recipient(), bob.recipient()]).unwrap();
+
+        // Swap Bob's stanza for one made for Mallory.  Alice's stanza
+        // still opens, but the header no longer matches the data.
+        let (header, ciphertext) = split_header(&data);
+        let other = encrypt(PLAINTEXT, &[mallory.recipient()]).unwrap();
+        let (other_header, _) = split_header(&other);
+        let bob_stanza = header.lines().nth(2).unwrap();
+        let mallory_stanza = other_header.lines().nth(1).unwrap();
+
+        let mut tampered = header.replace(bob_stanza, mallory_stanza).into_bytes();
+        tampered.extend_from_slice(ciphertext);
+
+        assert!(decrypt(&data, &alice).is_ok());
+        assert!(decrypt(&tampered, &alice).is_err());
+    }
+
+    #[test]
+    fn low_order_recipient_is_rejected() {
+        let zero = Recipient {
+            public: [0u8; KEY_LEN],
+        };
+
+        assert!(encrypt(PLAINTEXT, &[zero]).is_err());
+    }
+}
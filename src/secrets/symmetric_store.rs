@@ -1,21 +1,78 @@
 use std::error;
 
+use ring::digest;
 use tindercrypt::cryptors::RingCryptor;
 
-use crate::secrets;
+/// A short, stable identifier for a key, which is safe to show and to
+/// record in export files: the start of its SHA-256 hash, in hex.
+pub fn fingerprint(key: &str) -> String {
+    digest::digest(&digest::SHA256, key.as_bytes()).as_ref()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Escrowed keys look like `keez-key:<key>:<fingerprint>`, with the
+// key in URL-safe base64, so they survive being pasted into chat,
+// password managers or printouts.
+const ESCROW_PREFIX: &str = "keez-key:";
+
+/// Encode a key for backing it up or handing it to a teammate.  The
+/// fingerprint is included, so that a damaged copy is noticed on
+/// import rather than when an export fails to decrypt.
+pub fn escrow_encode(key: &str) -> String {
+    format!(
+        "{}{}:{}",
+        ESCROW_PREFIX,
+        base64::encode_config(key.as_bytes(), base64::URL_SAFE_NO_PAD),
+        fingerprint(key)
+    )
+}
+
+/// Decode a key written by `escrow_encode`, checking its fingerprint.
+pub fn escrow_decode(encoded: &str) -> Result<String, Box<dyn error::Error>> {
+    let body = encoded
+        .trim()
+        .strip_prefix(ESCROW_PREFIX)
+        .ok_or("not an escrowed keez key, expected it to start with keez-key:")?;
+    let (encoded_key, expected) = body
+        .split_once(':')
+        .ok_or("escrowed key is missing its fingerprint")?;
+
+    let key = String::from_utf8(base64::decode_config(encoded_key, base64::URL_SAFE_NO_PAD)?)?;
+    if key.is_empty() {
+        return Err("escrowed key is empty".into());
+    }
+    if fingerprint(&key) != expected {
+        return Err(format!(
+            "escrowed key doesn't match its fingerprint {}, it may have been damaged",
+            expected
+        )
+        .into());
+    }
+    Ok(key)
+}
 
-pub fn encrypt(input: String) -> Result<Vec<u8>, Box<dyn error::Error>> {
+/// Encrypt with a key derived from `passphrase`.  The key derivation
+/// parameters (PBKDF2-HMAC-SHA256, its iteration count and salt) are
+/// stored in the ciphertext's metadata, so that the passphrase is all
+/// that's needed to decrypt, on any machine.
+pub fn encrypt_with_passphrase(
+    input: String,
+    passphrase: &str,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
     let plaintext = input.as_bytes();
-    let pass = secrets::keychain_access::get_symmetric_key();
     let cryptor = RingCryptor::new();
 
-    Ok(cryptor.seal_with_passphrase(pass?.as_bytes(), plaintext)?)
+    Ok(cryptor.seal_with_passphrase(passphrase.as_bytes(), plaintext)?)
 }
 
-pub fn decrypt(input: Vec<u8>) -> Result<String, Box<dyn error::Error>> {
-    let pass = secrets::keychain_access::get_symmetric_key();
+pub fn decrypt_with_passphrase(
+    input: Vec<u8>,
+    passphrase: &str,
+) -> Result<String, Box<dyn error::Error>> {
     let cryptor = RingCryptor::new();
 
-    let plaintext = cryptor.open(pass?.as_bytes(), &input)?;
+    let plaintext = cryptor.open(passphrase.as_bytes(), &input)?;
     Ok(String::from_utf8(plaintext)?)
 }